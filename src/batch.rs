@@ -0,0 +1,163 @@
+use crate::prom;
+use alloy::api::{SetRequest, SetRequestTarget};
+use alloy::{Address, OutputValue};
+use std::collections::BTreeMap;
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// Accumulates set requests produced over multiple ticks, coalescing them per address.
+/// The last value written to an address within a window wins.
+pub(crate) struct SetRequestBatch {
+    window: Duration,
+    window_start: Option<Instant>,
+    values_by_address: BTreeMap<Address, OutputValue>,
+    other: Vec<SetRequest>,
+    num_pushed: usize,
+}
+
+impl SetRequestBatch {
+    /// Creates a new batch with the given window.
+    /// A window of zero results in one batch per tick.
+    pub(crate) fn new(window: Duration) -> SetRequestBatch {
+        SetRequestBatch {
+            window,
+            window_start: None,
+            values_by_address: BTreeMap::new(),
+            other: Vec::new(),
+            num_pushed: 0,
+        }
+    }
+
+    /// Adds set requests to the batch, starting a new window if necessary.
+    pub(crate) fn push(&mut self, set_requests: &[SetRequest]) {
+        if self.window_start.is_none() {
+            self.window_start = Some(Instant::now());
+        }
+        self.num_pushed += set_requests.len();
+
+        for req in set_requests {
+            match &req.target {
+                SetRequestTarget::Address(addr) => {
+                    self.values_by_address.insert(*addr, req.value);
+                }
+                _ => self.other.push(req.clone()),
+            }
+        }
+    }
+
+    /// Returns whether the current window has passed and the batch should be sent.
+    pub(crate) fn is_due(&self) -> bool {
+        self.window_start
+            .map(|start| start.elapsed() >= self.window)
+            .unwrap_or(false)
+    }
+
+    /// Moves the coalesced set requests into the given vector, ordered by address, and resets
+    /// the batch.
+    pub(crate) fn take(&mut self, into: &mut Vec<SetRequest>) {
+        into.clear();
+        into.extend(
+            self.values_by_address
+                .iter()
+                .map(|(addr, value)| SetRequest {
+                    value: *value,
+                    target: SetRequestTarget::Address(*addr),
+                }),
+        );
+        into.append(&mut self.other);
+
+        if !into.is_empty() {
+            prom::SEND_COALESCING_RATIO.set(self.num_pushed as f64 / into.len() as f64);
+        }
+
+        self.values_by_address.clear();
+        self.window_start = None;
+        self.num_pushed = 0;
+    }
+
+    /// Puts set requests which could not be sent back into the batch, so that they are sent
+    /// with the next one.
+    /// Values pushed in the meantime take precedence over requeued ones.
+    pub(crate) fn requeue(&mut self, set_requests: &[SetRequest]) {
+        if self.window_start.is_none() {
+            self.window_start = Some(Instant::now());
+        }
+
+        let newer = mem::take(&mut self.other);
+        for req in set_requests {
+            match &req.target {
+                SetRequestTarget::Address(addr) => {
+                    self.values_by_address.entry(*addr).or_insert(req.value);
+                }
+                _ => self.other.push(req.clone()),
+            }
+        }
+        self.other.extend(newer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(addr: Address, value: OutputValue) -> SetRequest {
+        SetRequest {
+            value,
+            target: SetRequestTarget::Address(addr),
+        }
+    }
+
+    fn values(batch: &mut SetRequestBatch) -> Vec<(Address, OutputValue)> {
+        let mut reqs = Vec::new();
+        batch.take(&mut reqs);
+        reqs.iter()
+            .map(|req| match req.target {
+                SetRequestTarget::Address(addr) => (addr, req.value),
+                _ => panic!("unexpected target {:?}", req.target),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ticks_within_window_collapse_into_one_batch() {
+        let mut batch = SetRequestBatch::new(Duration::from_secs(3600));
+        assert!(!batch.is_due());
+
+        batch.push(&[set(2, 20), set(1, 10)]);
+        batch.push(&[set(1, 11)]);
+        batch.push(&[set(1, 12), set(3, 30)]);
+        assert!(!batch.is_due());
+
+        assert_eq!(values(&mut batch), vec![(1, 12), (2, 20), (3, 30)]);
+        assert!(values(&mut batch).is_empty());
+    }
+
+    #[test]
+    fn zero_window_sends_every_tick() {
+        let mut batch = SetRequestBatch::new(Duration::ZERO);
+        assert!(!batch.is_due());
+
+        batch.push(&[set(1, 10)]);
+        assert!(batch.is_due());
+        assert_eq!(values(&mut batch), vec![(1, 10)]);
+        assert!(!batch.is_due());
+    }
+
+    #[test]
+    fn requeue_keeps_failed_values() {
+        let mut batch = SetRequestBatch::new(Duration::ZERO);
+        batch.requeue(&[set(1, 10), set(2, 20)]);
+
+        assert!(batch.is_due());
+        assert_eq!(values(&mut batch), vec![(1, 10), (2, 20)]);
+    }
+
+    #[test]
+    fn requeue_does_not_override_newer_values() {
+        let mut batch = SetRequestBatch::new(Duration::ZERO);
+        batch.push(&[set(1, 11)]);
+        batch.requeue(&[set(1, 10), set(2, 20)]);
+
+        assert_eq!(values(&mut batch), vec![(1, 11), (2, 20)]);
+    }
+}
@@ -1,17 +1,147 @@
-use anyhow::Context;
 use anyhow::Result;
+use anyhow::{ensure, Context};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// The structure of the configuration file.
 #[derive(Deserialize, Clone, Debug)]
 pub(crate) struct Config {
     pub(crate) prometheus_listen_address: String,
     pub(crate) http_listen_address: String,
-    pub(crate) amqp_server_address: String,
+    /// Address of the AMQP broker to receive events from.
+    /// Event input is currently disabled, so this is optional.
+    #[serde(default)]
+    pub(crate) amqp_server_address: Option<String>,
     pub(crate) submarine_http_url: String,
-    pub(crate) fixtures_path: String,
+    /// Path to a directory of fixtures, or a .zip or .tar.gz bundle thereof, or a list of these.
+    /// Directories are searched recursively.
+    pub(crate) fixtures_path: FixturesPath,
+    /// Interval in milliseconds at which fixtures are ticked.
+    /// Must be at least 1.
+    #[serde(default = "default_tick_interval_ms")]
+    pub(crate) tick_interval_ms: u64,
+    /// Interval in seconds at which tick and send statistics are logged.
+    /// Must be at least 1.
+    #[serde(default = "default_stats_interval_secs")]
+    pub(crate) stats_interval_secs: u64,
+    /// Window in milliseconds over which set requests are batched before being sent to
+    /// Submarine, coalesced per address.
+    /// A window of zero sends set requests after every tick.
+    #[serde(default)]
+    pub(crate) send_window_ms: u64,
+    /// Number of consecutive failures to post set requests to Submarine after which the
+    /// connection is considered lost.
+    /// While lost, posting is retried with backoff, and the connection is re-validated.
+    #[serde(default = "default_submarine_retry_threshold")]
+    pub(crate) submarine_retry_threshold: u32,
+    /// Whether to enable debugging routes in the HTTP API.
+    #[serde(default)]
+    pub(crate) debug_api: bool,
+    /// Interval in milliseconds within which identical warnings, e.g., about failing fixture
+    /// ticks, are logged only once.
+    #[serde(default = "default_warning_interval_ms")]
+    pub(crate) warning_interval_ms: u64,
+    /// Duration in milliseconds over which outputs of newly loaded fixtures are ramped up from
+    /// LOW to their targets, to avoid inrush currents.
+    /// A duration of zero disables this.
+    #[serde(default)]
+    pub(crate) startup_fade_ms: u64,
+    /// Default duration in milliseconds over which outputs crossfade when a fixture switches
+    /// programs.
+    /// A duration of zero disables this.
+    #[serde(default)]
+    pub(crate) program_fade_ms: u64,
+    /// Whether to skip sending the outputs of a fixture if they are identical to those of its
+    /// previous tick.
+    #[serde(default)]
+    pub(crate) suppress_unchanged_outputs: bool,
+    /// Whether to only send outputs whose value changed since it was last sent.
+    /// Unlike `suppress_unchanged_outputs`, this applies per address.
+    #[serde(default)]
+    pub(crate) send_changed_outputs_only: bool,
+    /// Whether to tick fixtures in parallel.
+    /// Disabling this can make debugging easier, e.g., by keeping log output in order.
+    #[serde(default = "default_parallel_ticks")]
+    pub(crate) parallel_ticks: bool,
+    /// Whether to watch the fixtures directory and reload fixtures when their sources change.
+    #[serde(default)]
+    pub(crate) watch_fixtures: bool,
+    /// File to save the selected programs and parameter values of all fixtures to.
+    /// If set, the saved state is restored on startup.
+    #[serde(default)]
+    pub(crate) state_file: Option<String>,
+    /// Whether to set all outputs of the universe to LOW when shutting down via Ctrl-C.
+    /// This includes outputs not controlled by any fixture.
+    #[serde(default)]
+    pub(crate) blackout_on_shutdown: bool,
+    /// Whether multiple fixtures may drive the same output address.
+    /// If not, overlapping fixtures fail startup, otherwise they are logged.
+    #[serde(default)]
+    pub(crate) allow_output_overlap: bool,
+    /// Whether a program writing outputs its fixture didn't declare fails the tick.
+    /// If not, these outputs are dropped and logged.
+    #[serde(default)]
+    pub(crate) strict_program_outputs: bool,
+    /// Local overrides of output aliases, from the alias of the universe to the local alias.
+    /// Both aliases resolve in fixtures and programs.
+    #[serde(default)]
+    pub(crate) alias_overrides: HashMap<String, String>,
+    /// File to read the universe config from if Submarine is unreachable on startup, in the same
+    /// JSON format Submarine serves it in.
+    /// In that case, the file is re-read on SIGHUP.
+    #[serde(default)]
+    pub(crate) universe_config_file: Option<String>,
+    /// Location of the installation, used to calculate sunrise and sunset for programs.
+    #[serde(default)]
+    pub(crate) location: Option<Location>,
+}
+
+/// A geographic location, in degrees.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub(crate) struct Location {
+    pub(crate) latitude: f64,
+    /// Positive east of Greenwich.
+    pub(crate) longitude: f64,
+}
+
+/// One or multiple paths to fixtures.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub(crate) enum FixturesPath {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl FixturesPath {
+    /// Returns all configured paths, in order.
+    pub(crate) fn paths(&self) -> Vec<PathBuf> {
+        match self {
+            FixturesPath::Single(path) => vec![path.into()],
+            FixturesPath::Multiple(paths) => paths.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+fn default_tick_interval_ms() -> u64 {
+    5
+}
+
+fn default_stats_interval_secs() -> u64 {
+    2
+}
+
+fn default_warning_interval_ms() -> u64 {
+    5000
+}
+
+fn default_submarine_retry_threshold() -> u32 {
+    10
+}
+
+fn default_parallel_ticks() -> bool {
+    true
 }
 
 impl Config {
@@ -21,7 +151,51 @@ impl Config {
 
         let cfg: Config =
             serde_yaml::from_slice(contents.as_slice()).context("unable to parse config")?;
+        ensure!(
+            cfg.tick_interval_ms >= 1,
+            "tick_interval_ms must be at least 1"
+        );
+        ensure!(
+            cfg.stats_interval_secs >= 1,
+            "stats_interval_secs must be at least 1"
+        );
 
         Ok(cfg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL_CONFIG: &str = r#"
+prometheus_listen_address: "127.0.0.1:4343"
+http_listen_address: "127.0.0.1:3545"
+submarine_http_url: "http://127.0.0.1:3069"
+fixtures_path: "./fixtures"
+"#;
+
+    fn read(contents: &str) -> Result<Config> {
+        let file = tempfile::NamedTempFile::new()?;
+        fs::write(file.path(), contents)?;
+        Config::read_from_file(file.path())
+    }
+
+    #[test]
+    fn amqp_server_address_is_optional() {
+        let cfg = read(MINIMAL_CONFIG).unwrap();
+        assert!(cfg.amqp_server_address.is_none());
+
+        let cfg = read(&format!(
+            "{}amqp_server_address: \"127.0.0.1:5672\"\n",
+            MINIMAL_CONFIG
+        ))
+        .unwrap();
+        assert_eq!(cfg.amqp_server_address.as_deref(), Some("127.0.0.1:5672"));
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        Config::read_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/dist/config.yaml")).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use crate::prom;
+use log::{error, info};
+use std::time::{Duration, Instant};
+
+/// Backoff between attempts to reach Submarine after the connection was considered lost.
+const MIN_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Tracks the health of the connection to Submarine, based on consecutive failed requests.
+/// After `threshold` consecutive failures, the connection is considered lost, and requests are
+/// only attempted with exponential backoff until one succeeds.
+pub(crate) struct ConnectionHealth {
+    threshold: u32,
+    consecutive_failures: u32,
+    backoff: Duration,
+    next_attempt: Option<Instant>,
+}
+
+impl ConnectionHealth {
+    pub(crate) fn new(threshold: u32) -> ConnectionHealth {
+        prom::SUBMARINE_CONNECTED.set(1);
+        ConnectionHealth {
+            threshold,
+            consecutive_failures: 0,
+            backoff: MIN_BACKOFF,
+            next_attempt: None,
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.consecutive_failures < self.threshold
+    }
+
+    /// Returns whether a request should be attempted now.
+    pub(crate) fn should_attempt(&self, now: Instant) -> bool {
+        match self.next_attempt {
+            Some(next_attempt) => now >= next_attempt,
+            None => true,
+        }
+    }
+
+    pub(crate) fn record_success(&mut self) {
+        if !self.is_connected() {
+            info!(
+                "connection to submarine restored after {} failed attempts",
+                self.consecutive_failures
+            );
+            prom::SUBMARINE_CONNECTED.set(1);
+        }
+        self.consecutive_failures = 0;
+        self.backoff = MIN_BACKOFF;
+        self.next_attempt = None;
+    }
+
+    /// Records a failed request.
+    /// Returns whether the connection was just considered lost, in which case it should be
+    /// re-validated.
+    pub(crate) fn record_failure(&mut self, now: Instant) -> bool {
+        prom::SUBMARINE_SEND_ERRORS.inc();
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.is_connected() {
+            return false;
+        }
+
+        self.next_attempt = Some(now + self.backoff);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+
+        if self.consecutive_failures == self.threshold {
+            error!(
+                "posting set requests to submarine failed {} times in a row, considering connection lost",
+                self.consecutive_failures
+            );
+            prom::SUBMARINE_CONNECTED.set(0);
+            return true;
+        }
+        false
+    }
+}
@@ -1,13 +1,38 @@
 use crate::runtime::runtime::Runtime;
+use crate::stats::TickStats;
 use alloy::config::UniverseConfig;
 use anyhow::Context;
 use anyhow::Result;
+use arc_swap::ArcSwap;
+use futures::Stream;
+use reqwest::Url;
 use std::fmt;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{watch, Mutex};
 use warp::Filter;
 
+/// The startup state of the process, reported via the health endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Readiness {
+    ConnectingToSubmarine,
+    LoadingFixtures,
+    Ready,
+}
+
+impl fmt::Display for Readiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Readiness::ConnectingToSubmarine => {
+                f.write_str("initializing: connecting to submarine")
+            }
+            Readiness::LoadingFixtures => f.write_str("initializing: loading fixtures"),
+            Readiness::Ready => f.write_str("ready"),
+        }
+    }
+}
+
 /// Wrapper to pretty-print optional values.
 struct OptFmt<T>(Option<T>);
 
@@ -21,109 +46,662 @@ impl<T: fmt::Display> fmt::Display for OptFmt<T> {
     }
 }
 
+/// Binds the HTTP listener.
+/// The listener is shared between the health server used during startup and the full API server.
+pub(crate) async fn bind(addr: SocketAddr) -> Result<Arc<TcpListener>> {
+    let listener = TcpListener::bind(addr).await.context("unable to bind")?;
+    Ok(Arc::new(listener))
+}
+
+/// Accepts connections on a shared listener.
+fn incoming(listener: Arc<TcpListener>) -> impl Stream<Item = std::io::Result<TcpStream>> {
+    futures::stream::unfold(listener, |listener| async move {
+        let conn = listener.accept().await.map(|(stream, _)| stream);
+        Some((conn, listener))
+    })
+}
+
+/// Serves only the health endpoint, until the process is ready.
+pub(crate) async fn run_health_server(
+    listener: Arc<TcpListener>,
+    mut readiness: watch::Receiver<Readiness>,
+) -> Result<()> {
+    let routes = filters::healthz(readiness.clone()).with(warp::log::custom(log_request));
+
+    warp::serve(routes)
+        .serve_incoming_with_graceful_shutdown(incoming(listener), async move {
+            // If the sender is dropped, startup failed, so we can also shut down.
+            let _ = readiness.wait_for(|r| *r == Readiness::Ready).await;
+        })
+        .await;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn run_server(
-    addr: SocketAddr,
+    listener: Arc<TcpListener>,
     state: Arc<Mutex<Runtime>>,
-    universe: Arc<UniverseConfig>,
+    stats: Arc<Mutex<TickStats>>,
+    universe: Arc<ArcSwap<UniverseConfig>>,
+    debug_api: bool,
+    readiness: watch::Receiver<Readiness>,
+    submarine_base_url: Url,
+    submarine_client: reqwest::Client,
 ) -> Result<()> {
-    let api = filters::docs().or(filters::api(state, universe));
-
-    let routes = api.with(warp::log::custom(move |info: warp::log::Info<'_>| {
-        // This is the exact same as warp::log::log("api"), but logging at DEBUG instead of INFO.
-        log::debug!(
-            target: "api",
-            "{} \"{} {} {:?}\" {} \"{}\" \"{}\" {:?}",
-            OptFmt(info.remote_addr()),
-            info.method(),
-            info.path(),
-            info.version(),
-            info.status().as_u16(),
-            OptFmt(info.referer()),
-            OptFmt(info.user_agent()),
-            info.elapsed(),
-        );
-    }));
+    let api = filters::docs()
+        .or(filters::healthz(readiness))
+        .or(filters::api(
+            state,
+            stats,
+            universe,
+            debug_api,
+            submarine_base_url,
+            submarine_client,
+        ));
+
+    let routes = api.with(warp::log::custom(log_request));
 
     // Start up the server...
-    let (_, fut) = warp::serve(routes)
-        .try_bind_ephemeral(addr)
-        .context("unable to bind")?;
-    fut.await;
+    warp::serve(routes).run_incoming(incoming(listener)).await;
 
     Ok(())
 }
 
+fn log_request(info: warp::log::Info<'_>) {
+    // This is the exact same as warp::log::log("api"), but logging at DEBUG instead of INFO.
+    log::debug!(
+        target: "api",
+        "{} \"{} {} {:?}\" {} \"{}\" \"{}\" {:?}",
+        OptFmt(info.remote_addr()),
+        info.method(),
+        info.path(),
+        info.version(),
+        info.status().as_u16(),
+        OptFmt(info.referer()),
+        OptFmt(info.user_agent()),
+        info.elapsed(),
+    );
+}
+
 mod filters {
-    use super::handlers;
+    use super::{handlers, Readiness};
+    use crate::runtime::fixture::{FixtureState, ParameterRequest, ProgramParametersRequest};
     use crate::runtime::runtime::Runtime;
+    use crate::stats::TickStats;
     use alloy::config::UniverseConfig;
-    use alloy::program::ParameterSetRequest;
+    use arc_swap::ArcSwap;
     use futures::future;
     use log::warn;
+    use reqwest::Url;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::collections::BTreeMap;
     use std::sync::Arc;
-    use tokio::sync::Mutex;
+    use tokio::sync::{watch, Mutex};
     use warp::hyper::body::Bytes;
     use warp::{body, path, Filter};
 
-    // TODO add route to cycle active program per fixture
-    // TODO add route to cycle discrete parameter value
-    // TODO add route to set multiple parameters at once
     // TODO add route to enable/disable/cycle multiple programs at one
 
+    /// A route of the API, listed by [docs] and described by [openapi_document].
+    /// This must be kept in sync with the filters combined in [api].
+    struct Route {
+        method: &'static str,
+        /// The path, with parameters given as `:name`.
+        path: &'static str,
+        description: &'static str,
+        body: RouteBody,
+    }
+
+    /// The body a route expects.
+    enum RouteBody {
+        None,
+        /// Plain text.
+        Text,
+        /// JSON without a schema in the OpenAPI document.
+        Json,
+        /// JSON following a schema of the OpenAPI document.
+        Schema(&'static str),
+    }
+
+    const ROUTES: &[Route] = &[
+        Route {
+            method: "GET",
+            path: "/healthz",
+            description: "Get readiness, returns 503 during startup.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/pause",
+            description: "Pause the tick loop, holding all outputs.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/resume",
+            description: "Resume the tick loop.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/unsolo",
+            description: "Clear the solo, so that all fixtures produce outputs again.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/tick",
+            description: "Run a single tick while paused, send and return its outputs.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/preview",
+            description: "Get the last value and position of every output, for visualizers.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/master_brightness",
+            description: "Get the master brightness, as {\"value\": ...}.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/master_brightness",
+            description: "Set the master brightness, provide {\"value\": ...} in [0, 1] as JSON. This scales all outputs not declared binary.",
+            body: RouteBody::Schema("MasterBrightness"),
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/stats",
+            description: "Get tick loop statistics, averaged over the last statistics interval, and the number of fixtures.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/openapi.json",
+            description: "Get an OpenAPI description of these routes.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/ws",
+            description: "Upgrade to a WebSocket streaming program switches and parameter changes as JSON. Pass ?snapshot=true to receive the current state first.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/programs/set_active",
+            description: "Set active program of multiple fixtures at once, provide {\"program\": ..., \"fixtures\": [...] or \"*\"} as JSON in the body.",
+            body: RouteBody::Json,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/tags/:tag/set_active_program",
+            description: "Set active program of all fixtures with a tag, provide the name as text in the body.",
+            body: RouteBody::Text,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures",
+            description: "List fixtures, optionally only those with a tag via ?tag=.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture",
+            description: "Get single fixture.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "DELETE",
+            path: "/api/v1/fixtures/:fixture",
+            description: "Remove fixture until restart, pass ?blackout=true to set its outputs to LOW.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/solo",
+            description: "Solo a fixture, holding the outputs of all other fixtures at LOW until /api/v1/unsolo.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/enabled",
+            description: "Enable or disable a fixture, provide true or false as JSON. Disabled fixtures are not ticked and keep their last outputs.",
+            body: RouteBody::Json,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/outputs",
+            description: "Get the most recent output values of a fixture, with the time they were produced.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/changes",
+            description: "Get the parameters changed after the revision passed as ?since=..., and the current revision.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/programs",
+            description: "List programs for fixture.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/set_active_program",
+            description: "Set active program by name, provide the name as text in the body. Pass ?fade_ms=... to override the crossfade duration.",
+            body: RouteBody::Text,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/cycle_active_program",
+            description: "Cycle to the next program, skipping MANUAL and EXTERNAL unless ?include_all=true is passed. Pass ?fade_ms=... to override the crossfade duration.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/export",
+            description: "Export selected program and parameter values.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/import",
+            description: "Import selected program and parameter values, provide an exported state as JSON in the body.",
+            body: RouteBody::Json,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/reload",
+            description: "Reload fixture and its programs from disk.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/programs/:program",
+            description: "Get single program.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program",
+            description: "Set parameter values, provide the program as JSON in the body, as returned by GET.",
+            body: RouteBody::Json,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program/reload",
+            description: "Reload a Lua program from disk, keeping the values of parameters which still exist with the same type and a valid value.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program/reset_parameters",
+            description: "Reset all parameters of a program to the values declared during setup. Returns the updated program.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/programs/:program/parameters",
+            description: "List parameters for program.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program/parameters",
+            description: "Set multiple parameter values, provide a map of parameter names to alloy::program::ParameterSetRequest (or {\"Color\":{\"value\":[r,g,b]}}) as JSON in the body.",
+            body: RouteBody::Schema("ParameterSetRequests"),
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/programs/:program/parameters/:parameter",
+            description: "Get single parameter.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program/parameters/:parameter",
+            description: "Set parameter value, provide an alloy::program::ParameterSetRequest (or {\"Color\":{\"value\":[r,g,b]}}) as JSON in the body. Returns the updated parameter.",
+            body: RouteBody::Schema("ParameterSetRequest"),
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program/parameters/:parameter/cycle",
+            description: "Cycle discrete or integer parameter value, pass ?dir=back to step backwards.",
+            body: RouteBody::None,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program/parameters/:parameter/increment",
+            description: "Change a parameter value by a delta, provide {\"delta\": 0.05} as JSON. Continuous and integer values are clamped, discrete levels wrap.",
+            body: RouteBody::Json,
+        },
+        Route {
+            method: "POST",
+            path: "/api/v1/fixtures/:fixture/programs/:program/preview",
+            description: "Compute the outputs of a program without sending them or changing any state, optionally provide {\"parameters\": {...}, \"at\": seconds since the program was enabled} as JSON.",
+            body: RouteBody::Json,
+        },
+        Route {
+            method: "GET",
+            path: "/api/v1/fixtures/:fixture/programs/:program/debug",
+            description: "Get scalar globals of a Lua program, if debug_api is enabled.",
+            body: RouteBody::None,
+        },
+    ];
+
     pub(crate) fn docs(
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
         path::end().map(|| {
-            let routes = vec![
-                "GET  /api/v1/fixtures                                                        List fixtures.",
-                "GET  /api/v1/fixtures/:fixture                                               Get single fixture.",
-                "GET  /api/v1/fixtures/:fixture/programs                                      List programs for fixture.",
-                "POST /api/v1/fixtures/:fixture/set_active_program                            Set active program by name, provide the name as text in the body.",
-                "POST /api/v1/fixtures/:fixture/cycle_active_program                          Cycle to the next program, skipping MANUAL and EXTERNAL.",
-                "GET  /api/v1/fixtures/:fixture/programs/:program                             Get single program.",
-                "GET  /api/v1/fixtures/:fixture/programs/:program/parameters                  List parameters for program.",
-                "GET  /api/v1/fixtures/:fixture/programs/:program/parameters/:parameter       Get single parameter.",
-                "POST /api/v1/fixtures/:fixture/programs/:program/parameters/:parameter       Set parameter value, provide an alloy::program::ParameterSetRequest as JSON in the body.",
-                "POST /api/v1/fixtures/:fixture/programs/:program/parameters/:parameter/cycle Cycle discrete parameter value.",
-                "" // For newline at the end
-            ];
+            let mut routes: Vec<_> = ROUTES
+                .iter()
+                .map(|r| format!("{:<4} {:<71} {}", r.method, r.path, r.description))
+                .collect();
+            routes.push(String::new()); // For newline at the end
             routes.join("\n")
         })
     }
 
+    /// Builds an OpenAPI document describing [ROUTES].
+    fn openapi_document() -> serde_json::Value {
+        let mut paths = serde_json::Map::new();
+        for route in ROUTES {
+            let mut path = Vec::new();
+            let mut parameters = Vec::new();
+            for segment in route.path.split('/') {
+                match segment.strip_prefix(':') {
+                    Some(name) => {
+                        path.push(format!("{{{}}}", name));
+                        parameters.push(json!({
+                            "name": name,
+                            "in": "path",
+                            "required": true,
+                            "schema": {"type": "string"},
+                        }));
+                    }
+                    None => path.push(segment.to_string()),
+                }
+            }
+
+            let mut operation = json!({
+                "summary": route.description,
+                "parameters": parameters,
+                "responses": {"200": {"description": "OK"}},
+            });
+            let content = match route.body {
+                RouteBody::None => None,
+                RouteBody::Text => Some(json!({"text/plain": {"schema": {"type": "string"}}})),
+                RouteBody::Json => Some(json!({"application/json": {"schema": {}}})),
+                RouteBody::Schema(name) => Some(json!({"application/json": {
+                    "schema": {"$ref": format!("#/components/schemas/{}", name)},
+                }})),
+            };
+            if let Some(content) = content {
+                operation["requestBody"] = json!({"required": true, "content": content});
+            }
+
+            let item = paths.entry(path.join("/")).or_insert_with(|| json!({}));
+            item[route.method.to_lowercase()] = operation;
+        }
+
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "kaleidoscope",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "paths": paths,
+            "components": {"schemas": {
+                "ParameterSetRequest": {
+                    "description": "An alloy::program::ParameterSetRequest, or a color.",
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "required": ["Discrete"],
+                            "properties": {"Discrete": {
+                                "type": "object",
+                                "required": ["level"],
+                                "properties": {"level": {"type": "string"}},
+                            }},
+                        },
+                        {
+                            "type": "object",
+                            "required": ["Continuous"],
+                            "properties": {"Continuous": {
+                                "type": "object",
+                                "required": ["value"],
+                                "properties": {"value": {"type": "number"}},
+                            }},
+                        },
+                        {
+                            "type": "object",
+                            "required": ["Color"],
+                            "properties": {"Color": {
+                                "type": "object",
+                                "required": ["value"],
+                                "properties": {"value": {
+                                    "type": "array",
+                                    "items": {"type": "number", "minimum": 0, "maximum": 1},
+                                    "minItems": 3,
+                                    "maxItems": 3,
+                                }},
+                            }},
+                        },
+                    ],
+                },
+                "ParameterSetRequests": {
+                    "description": "Parameter names mapped to the values to set.",
+                    "type": "object",
+                    "additionalProperties": {"$ref": "#/components/schemas/ParameterSetRequest"},
+                },
+                "MasterBrightness": {
+                    "type": "object",
+                    "required": ["value"],
+                    "properties": {"value": {"type": "number", "minimum": 0, "maximum": 1}},
+                },
+            }},
+        })
+    }
+
+    pub(crate) fn openapi(
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        let document = Arc::new(openapi_document());
+        warp::path!("openapi.json")
+            .and(path::end())
+            .and(warp::get())
+            .and(warp::any().map(move || document.clone()))
+            .and_then(handlers::get_openapi)
+    }
+
+    pub(crate) fn healthz(
+        readiness: watch::Receiver<Readiness>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("healthz")
+            .and(path::end())
+            .and(warp::get())
+            .and(warp::any().map(move || *readiness.borrow()))
+            .and_then(handlers::get_healthz)
+    }
+
     pub(crate) fn api(
         state: Arc<Mutex<Runtime>>,
-        universe: Arc<UniverseConfig>,
+        stats: Arc<Mutex<TickStats>>,
+        universe: Arc<ArcSwap<UniverseConfig>>,
+        debug_api: bool,
+        submarine_base_url: Url,
+        submarine_client: reqwest::Client,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
         warp::path!("api" / "v1" / ..).and(
-            fixtures_root(state.clone(), universe.clone())
+            pause(state.clone())
+                .or(resume(state.clone()))
+                .or(unsolo(state.clone()))
+                .or(tick(state.clone(), submarine_base_url, submarine_client))
+                .or(preview(state.clone(), universe.clone()))
+                .or(stats_root(state.clone(), stats))
+                .or(openapi())
+                .or(ws(state.clone()))
+                .or(master_brightness_get(state.clone()))
+                .or(master_brightness_set(state.clone()))
+                .or(programs_set_active(state.clone()))
+                .or(tags_tag_programs_set_active(state.clone()))
+                .or(fixtures_root(state.clone(), universe.clone()))
                 .or(fixtures_fixture_root(state.clone(), universe.clone()))
+                .or(fixtures_fixture_delete(state.clone()))
+                .or(fixtures_fixture_solo(state.clone()))
+                .or(fixtures_fixture_enabled(state.clone()))
+                .or(fixtures_fixture_outputs(state.clone()))
+                .or(fixtures_fixture_changes(state.clone()))
                 .or(fixtures_fixture_programs_set_active(state.clone()))
                 .or(fixtures_fixture_programs_cycle_active(state.clone()))
+                .or(fixtures_fixture_export(state.clone()))
+                .or(fixtures_fixture_import(state.clone()))
+                .or(fixtures_fixture_reload(state.clone(), universe.clone()))
                 .or(fixtures_fixture_programs_root(
                     state.clone(),
                     universe.clone(),
                 ))
                 .or(fixtures_fixture_programs_program_root(state.clone()))
+                .or(fixtures_fixture_programs_program_set(state.clone()))
+                .or(fixtures_fixture_programs_program_reload(state.clone()))
+                .or(fixtures_fixture_programs_program_reset_parameters(
+                    state.clone(),
+                ))
                 .or(fixtures_fixture_programs_program_parameters_root(
                     state.clone(),
                 ))
+                .or(fixtures_fixture_programs_program_parameters_set(
+                    state.clone(),
+                ))
                 .or(fixtures_fixture_programs_program_parameters_parameter_get(
                     state.clone(),
                 ))
                 .or(fixtures_fixture_programs_program_parameters_parameter_set(
                     state.clone(),
                 ))
-                .or(fixtures_fixture_programs_program_parameters_parameter_cycle(state.clone())),
+                .or(fixtures_fixture_programs_program_parameters_parameter_cycle(state.clone()))
+                .or(fixtures_fixture_programs_program_parameters_parameter_increment(state.clone()))
+                .or(fixtures_fixture_programs_program_preview(state.clone()))
+                .or(fixtures_fixture_programs_program_debug(
+                    state.clone(),
+                    debug_api,
+                )),
         )
     }
 
+    pub(crate) fn pause(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("pause")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and_then(handlers::post_pause)
+    }
+
+    pub(crate) fn resume(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("resume")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and_then(handlers::post_resume)
+    }
+
+    pub(crate) fn unsolo(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("unsolo")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and_then(handlers::post_unsolo)
+    }
+
+    pub(crate) fn tick(
+        state: Arc<Mutex<Runtime>>,
+        submarine_base_url: Url,
+        submarine_client: reqwest::Client,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("tick")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(warp::any().map(move || submarine_base_url.clone()))
+            .and(warp::any().map(move || submarine_client.clone()))
+            .and_then(handlers::post_tick)
+    }
+
+    pub(crate) fn preview(
+        state: Arc<Mutex<Runtime>>,
+        universe: Arc<ArcSwap<UniverseConfig>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("preview")
+            .and(path::end())
+            .and(warp::get())
+            .and(with_state(state))
+            .and(with_universe_config(universe))
+            .and_then(handlers::get_preview)
+    }
+
+    pub(crate) fn master_brightness_get(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("master_brightness")
+            .and(path::end())
+            .and(warp::get())
+            .and(with_state(state))
+            .and_then(handlers::get_master_brightness)
+    }
+
+    pub(crate) fn master_brightness_set(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("master_brightness")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(body::content_length_limit(1024).and(body::json()))
+            .and_then(handlers::post_master_brightness)
+    }
+
+    pub(crate) fn stats_root(
+        state: Arc<Mutex<Runtime>>,
+        stats: Arc<Mutex<TickStats>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("stats")
+            .and(path::end())
+            .and(warp::get())
+            .and(with_state(state))
+            .and(warp::any().map(move || stats.clone()))
+            .and_then(handlers::get_stats)
+    }
+
+    pub(crate) fn ws(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("ws")
+            .and(path::end())
+            .and(warp::ws())
+            .and(warp::query::<WsQuery>())
+            .and(with_state(state))
+            .and_then(handlers::get_ws)
+    }
+
     pub(crate) fn fixtures_root(
         state: Arc<Mutex<Runtime>>,
-        universe: Arc<UniverseConfig>,
+        universe: Arc<ArcSwap<UniverseConfig>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
         warp::path!("fixtures")
             .and(path::end())
             .and(warp::get())
+            .and(warp::query::<FixturesQuery>())
             .and(with_state(state))
             .and(with_universe_config(universe))
             .and_then(handlers::get_fixtures_root)
@@ -131,7 +709,7 @@ mod filters {
 
     pub(crate) fn fixtures_fixture_root(
         state: Arc<Mutex<Runtime>>,
-        universe: Arc<UniverseConfig>,
+        universe: Arc<ArcSwap<UniverseConfig>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
         warp::path!("fixtures" / String)
             .and(path::end())
@@ -141,9 +719,20 @@ mod filters {
             .and_then(handlers::get_fixtures_fixture_root)
     }
 
+    pub(crate) fn fixtures_fixture_delete(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String)
+            .and(path::end())
+            .and(warp::delete())
+            .and(warp::query::<RemoveFixtureQuery>())
+            .and(with_state(state))
+            .and_then(handlers::delete_fixtures_fixture)
+    }
+
     pub(crate) fn fixtures_fixture_programs_root(
         state: Arc<Mutex<Runtime>>,
-        universe: Arc<UniverseConfig>,
+        universe: Arc<ArcSwap<UniverseConfig>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
         warp::path!("fixtures" / String / "programs")
             .and(path::end())
@@ -153,6 +742,48 @@ mod filters {
             .and_then(handlers::get_fixtures_fixture_programs_root)
     }
 
+    pub(crate) fn fixtures_fixture_solo(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "solo")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and_then(handlers::post_fixtures_fixture_solo)
+    }
+
+    pub(crate) fn fixtures_fixture_enabled(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "enabled")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(body::content_length_limit(1024).and(body::json()))
+            .and_then(handlers::post_fixtures_fixture_enabled)
+    }
+
+    pub(crate) fn fixtures_fixture_outputs(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "outputs")
+            .and(path::end())
+            .and(warp::get())
+            .and(with_state(state))
+            .and_then(handlers::get_fixtures_fixture_outputs)
+    }
+
+    pub(crate) fn fixtures_fixture_changes(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "changes")
+            .and(path::end())
+            .and(warp::get())
+            .and(warp::query::<ChangesQuery>())
+            .and(with_state(state))
+            .and_then(handlers::get_fixtures_fixture_changes)
+    }
+
     pub(crate) fn fixtures_fixture_programs_set_active(
         state: Arc<Mutex<Runtime>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -160,20 +791,77 @@ mod filters {
             .and(path::end())
             .and(warp::post())
             .and(set_active_program_body())
+            .and(warp::query::<ProgramFadeQuery>())
             .and(with_state(state))
             .and_then(handlers::post_fixtures_fixture_set_program)
     }
 
+    pub(crate) fn programs_set_active(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("programs" / "set_active")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(body::content_length_limit(16 * 1024).and(body::json()))
+            .and_then(handlers::post_programs_set_active)
+    }
+
+    pub(crate) fn tags_tag_programs_set_active(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("tags" / String / "set_active_program")
+            .and(path::end())
+            .and(warp::post())
+            .and(set_active_program_body())
+            .and(with_state(state))
+            .and_then(handlers::post_tags_tag_set_program)
+    }
+
     pub(crate) fn fixtures_fixture_programs_cycle_active(
         state: Arc<Mutex<Runtime>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
         warp::path!("fixtures" / String / "cycle_active_program")
             .and(path::end())
             .and(warp::post())
+            .and(warp::query::<CycleProgramQuery>())
             .and(with_state(state))
             .and_then(handlers::post_fixtures_fixture_cycle_program)
     }
 
+    pub(crate) fn fixtures_fixture_export(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "export")
+            .and(path::end())
+            .and(warp::get())
+            .and(with_state(state))
+            .and_then(handlers::get_fixtures_fixture_export)
+    }
+
+    pub(crate) fn fixtures_fixture_import(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "import")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(fixture_state_body())
+            .and_then(handlers::post_fixtures_fixture_import)
+    }
+
+    pub(crate) fn fixtures_fixture_reload(
+        state: Arc<Mutex<Runtime>>,
+        universe: Arc<ArcSwap<UniverseConfig>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "reload")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(with_universe_config(universe))
+            .and_then(handlers::post_fixtures_fixture_reload)
+    }
+
     pub(crate) fn fixtures_fixture_programs_program_root(
         state: Arc<Mutex<Runtime>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -184,6 +872,17 @@ mod filters {
             .and_then(handlers::get_fixtures_fixture_programs_program_root)
     }
 
+    pub(crate) fn fixtures_fixture_programs_program_set(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "programs" / String)
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(program_metadata_body())
+            .and_then(handlers::post_fixtures_fixture_programs_program_root)
+    }
+
     pub(crate) fn fixtures_fixture_programs_program_parameters_root(
         state: Arc<Mutex<Runtime>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -194,6 +893,17 @@ mod filters {
             .and_then(handlers::get_fixtures_fixture_programs_program_parameters_root)
     }
 
+    pub(crate) fn fixtures_fixture_programs_program_parameters_set(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "programs" / String / "parameters")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(parameter_requests_body())
+            .and_then(handlers::post_fixtures_fixture_programs_program_parameters_root)
+    }
+
     pub(crate) fn fixtures_fixture_programs_program_parameters_parameter_get(
         state: Arc<Mutex<Runtime>>,
     ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -221,10 +931,67 @@ mod filters {
         warp::path!("fixtures" / String / "programs" / String / "parameters" / String / "cycle")
             .and(path::end())
             .and(warp::post())
+            .and(warp::query::<CycleParameterQuery>())
             .and(with_state(state))
             .and_then(handlers::post_fixtures_fixture_programs_program_parameters_parameter_cycle)
     }
 
+    pub(crate) fn fixtures_fixture_programs_program_parameters_parameter_increment(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "programs" / String / "parameters" / String / "increment")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(body::content_length_limit(1024).and(body::json()))
+            .and_then(
+                handlers::post_fixtures_fixture_programs_program_parameters_parameter_increment,
+            )
+    }
+
+    pub(crate) fn fixtures_fixture_programs_program_reload(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "programs" / String / "reload")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and_then(handlers::post_fixtures_fixture_programs_program_reload)
+    }
+
+    pub(crate) fn fixtures_fixture_programs_program_reset_parameters(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "programs" / String / "reset_parameters")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and_then(handlers::post_fixtures_fixture_programs_program_reset_parameters)
+    }
+
+    pub(crate) fn fixtures_fixture_programs_program_preview(
+        state: Arc<Mutex<Runtime>>,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "programs" / String / "preview")
+            .and(path::end())
+            .and(warp::post())
+            .and(with_state(state))
+            .and(body::content_length_limit(16 * 1024).and(body::json()))
+            .and_then(handlers::post_fixtures_fixture_programs_program_preview)
+    }
+
+    pub(crate) fn fixtures_fixture_programs_program_debug(
+        state: Arc<Mutex<Runtime>>,
+        debug_api: bool,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::path!("fixtures" / String / "programs" / String / "debug")
+            .and(path::end())
+            .and(warp::get())
+            .and(with_state(state))
+            .and(warp::any().map(move || debug_api))
+            .and_then(handlers::get_fixtures_fixture_programs_program_debug)
+    }
+
     fn with_state(
         state: Arc<Mutex<Runtime>>,
     ) -> impl Filter<Extract = (Arc<Mutex<Runtime>>,), Error = std::convert::Infallible> + Clone
@@ -233,19 +1000,38 @@ mod filters {
     }
 
     fn with_universe_config(
-        universe: Arc<UniverseConfig>,
+        universe: Arc<ArcSwap<UniverseConfig>>,
     ) -> impl Filter<Extract = (Arc<UniverseConfig>,), Error = std::convert::Infallible> + Clone
     {
-        warp::any().map(move || universe.clone())
+        warp::any().map(move || universe.load_full())
     }
 
     fn parameter_request_body(
-    ) -> impl Filter<Extract = (ParameterSetRequest,), Error = warp::Rejection> + Clone {
+    ) -> impl Filter<Extract = (ParameterRequest,), Error = warp::Rejection> + Clone {
         // When accepting a body, we want a JSON body
         // (and to reject huge payloads)...
         body::content_length_limit(1024).and(body::json())
     }
 
+    fn parameter_requests_body(
+    ) -> impl Filter<Extract = (BTreeMap<String, ParameterRequest>,), Error = warp::Rejection> + Clone
+    {
+        // One request per parameter, for consoles with many faders.
+        body::content_length_limit(16 * 1024).and(body::json())
+    }
+
+    fn program_metadata_body(
+    ) -> impl Filter<Extract = (ProgramParametersRequest,), Error = warp::Rejection> + Clone {
+        // Metadata contains all parameters of a program, including their levels.
+        body::content_length_limit(64 * 1024).and(body::json())
+    }
+
+    fn fixture_state_body(
+    ) -> impl Filter<Extract = (FixtureState,), Error = warp::Rejection> + Clone {
+        // Exported states contain all parameters of all programs, so these can be a bit larger.
+        body::content_length_limit(64 * 1024).and(body::json())
+    }
+
     fn set_active_program_body() -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone
     {
         body::content_length_limit(1024)
@@ -259,29 +1045,386 @@ mod filters {
             })
     }
 
-    #[derive(Debug)]
-    struct NonUtf8Body;
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct FixturesQuery {
+        pub(crate) tag: Option<String>,
+    }
 
-    impl warp::reject::Reject for NonUtf8Body {}
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct ChangesQuery {
+        /// The revision the client last saw, defaults to returning all parameters.
+        #[serde(default)]
+        pub(crate) since: u64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct WsQuery {
+        /// Whether to send the selected programs and parameter values of all fixtures first.
+        #[serde(default)]
+        pub(crate) snapshot: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct RemoveFixtureQuery {
+        #[serde(default)]
+        pub(crate) blackout: bool,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct SetActiveProgramsRequest {
+        pub(crate) program: String,
+        pub(crate) fixtures: FixtureSelection,
+    }
+
+    /// A list of fixture names, or `"*"` for all fixtures.
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    pub(crate) enum FixtureSelection {
+        Named(Vec<String>),
+        Wildcard(String),
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct CycleParameterQuery {
+        #[serde(default)]
+        pub(crate) dir: CycleDirection,
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub(crate) enum CycleDirection {
+        #[default]
+        Forward,
+        Back,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct CycleProgramQuery {
+        #[serde(default)]
+        pub(crate) include_all: bool,
+        /// Duration of the crossfade to the next program, overriding the configured default.
+        pub(crate) fade_ms: Option<u64>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    pub(crate) struct MasterBrightness {
+        pub(crate) value: f64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct IncrementParameterRequest {
+        pub(crate) delta: f64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct PreviewProgramRequest {
+        /// Parameter values to use instead of the current ones.
+        #[serde(default)]
+        pub(crate) parameters: BTreeMap<String, ParameterRequest>,
+        /// Seconds since the program was enabled.
+        #[serde(default)]
+        pub(crate) at: f64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub(crate) struct ProgramFadeQuery {
+        /// Duration of the crossfade to the program, overriding the configured default.
+        pub(crate) fade_ms: Option<u64>,
+    }
+
+    #[derive(Debug)]
+    struct NonUtf8Body;
+
+    impl warp::reject::Reject for NonUtf8Body {}
 }
 
 mod handlers {
+    use super::filters::{
+        ChangesQuery, CycleDirection, CycleParameterQuery, CycleProgramQuery, FixtureSelection,
+        FixturesQuery, IncrementParameterRequest, MasterBrightness, PreviewProgramRequest,
+        ProgramFadeQuery, RemoveFixtureQuery, SetActiveProgramsRequest, WsQuery,
+    };
+    use super::Readiness;
+    use crate::runtime::error::RuntimeError;
+    use crate::runtime::events::StateEvent;
+    use crate::runtime::fixture::{FixtureState, ParameterRequest, ProgramParametersRequest};
     use crate::runtime::runtime::Runtime;
+    use crate::stats::TickStats;
     use alloy::config::UniverseConfig;
-    use alloy::program::ParameterSetRequest;
-    use log::debug;
+    use futures::stream::SplitSink;
+    use futures::{SinkExt, StreamExt};
+    use log::{debug, warn};
+    use reqwest::Url;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
     use std::convert::Infallible;
     use std::sync::Arc;
-    use tokio::sync::Mutex;
+    use std::time::Duration;
+    use tokio::sync::broadcast::error::RecvError;
+    use tokio::sync::{broadcast, Mutex};
+    use warp::ws::{Message, WebSocket};
     use warp::{http, Rejection};
 
-    pub(crate) async fn get_fixtures_root(
+    /// Maps a runtime error to the status code reported to API clients.
+    fn error_status(err: &RuntimeError) -> http::StatusCode {
+        match err {
+            RuntimeError::UnknownFixture(_)
+            | RuntimeError::UnknownProgram(_)
+            | RuntimeError::UnknownParameter { .. } => http::StatusCode::NOT_FOUND,
+            RuntimeError::UnknownLevel(_)
+            | RuntimeError::OutOfRange { .. }
+            | RuntimeError::TypeMismatch(_)
+            | RuntimeError::NoLevels
+            | RuntimeError::BuiltinProgram(_) => http::StatusCode::BAD_REQUEST,
+            RuntimeError::VersionMismatch { .. } => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The outcome of one item of a batch request.
+    /// Serializes as `"ok"` or `{"error": "<message>"}`.
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub(crate) enum BatchResult {
+        Ok,
+        Error(String),
+    }
+
+    fn batch_results(
+        results: BTreeMap<String, Result<(), RuntimeError>>,
+    ) -> BTreeMap<String, BatchResult> {
+        results
+            .into_iter()
+            .map(|(name, res)| {
+                let res = match res {
+                    Ok(()) => BatchResult::Ok,
+                    Err(err) => BatchResult::Error(err.to_string()),
+                };
+                (name, res)
+            })
+            .collect()
+    }
+
+    /// Like `error_status`, but for errors which may or may not be caused by a `RuntimeError`.
+    fn anyhow_error_status(err: &anyhow::Error, fallback: http::StatusCode) -> http::StatusCode {
+        RuntimeError::find(err)
+            .map(error_status)
+            .unwrap_or(fallback)
+    }
+
+    pub(crate) async fn post_tick(
+        state: Arc<Mutex<Runtime>>,
+        submarine_base_url: Url,
+        submarine_client: reqwest::Client,
+    ) -> Result<Box<dyn warp::Reply>, Infallible> {
+        // Hold the lock until the set requests are posted, so that the tick loop can't be resumed
+        // in between.
+        let mut state = state.lock().await;
+        if !state.is_paused() {
+            return Ok(Box::new(http::StatusCode::CONFLICT));
+        }
+
+        let res = state.tick().map(|reqs| reqs.to_vec());
+        debug!("runtime::tick returned {:?}", res);
+        let set_requests = match res {
+            Ok(set_requests) => set_requests,
+            Err(_) => return Ok(Box::new(http::StatusCode::INTERNAL_SERVER_ERROR)),
+        };
+
+        if let Err(err) =
+            crate::post_set_requests(&submarine_base_url, &submarine_client, &set_requests).await
+        {
+            warn!("unable to post set requests to submarine: {:?}", err);
+            return Ok(Box::new(http::StatusCode::BAD_GATEWAY));
+        }
+
+        Ok(Box::new(warp::reply::json(&set_requests)))
+    }
+
+    pub(crate) async fn get_healthz(readiness: Readiness) -> Result<impl warp::Reply, Infallible> {
+        let status = if readiness == Readiness::Ready {
+            http::StatusCode::OK
+        } else {
+            http::StatusCode::SERVICE_UNAVAILABLE
+        };
+        Ok(warp::reply::with_status(readiness.to_string(), status))
+    }
+
+    pub(crate) async fn post_pause(
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        state.lock().await.set_paused(true);
+
+        Ok(http::StatusCode::OK)
+    }
+
+    pub(crate) async fn post_resume(
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        state.lock().await.set_paused(false);
+
+        Ok(http::StatusCode::OK)
+    }
+
+    pub(crate) async fn post_unsolo(
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let res = state.lock().await.set_solo(None);
+        debug!("runtime::set_solo returned {:?}", res);
+
+        Ok(match res {
+            Ok(()) => http::StatusCode::OK,
+            Err(err) => error_status(&err),
+        })
+    }
+
+    pub(crate) async fn post_fixtures_fixture_solo(
+        fixture_name: String,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let res = state.lock().await.set_solo(Some(&fixture_name));
+        debug!("runtime::set_solo returned {:?}", res);
+
+        Ok(match res {
+            Ok(()) => http::StatusCode::OK,
+            Err(err) => error_status(&err),
+        })
+    }
+
+    pub(crate) async fn get_preview(
         state: Arc<Mutex<Runtime>>,
         universe: Arc<UniverseConfig>,
     ) -> Result<impl warp::Reply, Infallible> {
-        let cfg = state.lock().await.alloy_metadata(universe.as_ref());
+        let feed = state.lock().await.preview(universe.as_ref());
+
+        Ok(warp::reply::json(&feed))
+    }
+
+    /// Statistics about the tick loop and loaded fixtures.
+    #[derive(Serialize)]
+    pub(crate) struct Stats {
+        #[serde(flatten)]
+        tick_loop: TickStats,
+        fixtures: usize,
+    }
+
+    pub(crate) async fn get_openapi(
+        document: Arc<serde_json::Value>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        Ok(warp::reply::json(document.as_ref()))
+    }
+
+    pub(crate) async fn get_ws(
+        ws: warp::ws::Ws,
+        query: WsQuery,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        // Subscribe before taking the snapshot, so that no change is missed in between.
+        let (snapshot, events) = {
+            let state = state.lock().await;
+            let events = state.subscribe();
+            (query.snapshot.then(|| state.snapshot()), events)
+        };
+        Ok(ws.on_upgrade(move |socket| forward_events(socket, snapshot, events)))
+    }
+
+    async fn forward_events(
+        socket: WebSocket,
+        snapshot: Option<StateEvent>,
+        mut events: broadcast::Receiver<StateEvent>,
+    ) {
+        let (mut tx, mut rx) = socket.split();
+        if let Some(snapshot) = snapshot {
+            if send_event(&mut tx, &snapshot).await.is_err() {
+                return;
+            }
+        }
 
-        Ok(warp::reply::json(&cfg))
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(missed)) => StateEvent::Lagged { missed },
+                    Err(RecvError::Closed) => break,
+                },
+                // Messages from the client are ignored, but we need to notice it disconnecting.
+                msg = rx.next() => match msg {
+                    Some(Ok(_)) => continue,
+                    _ => break,
+                },
+            };
+            if let Err(err) = send_event(&mut tx, &event).await {
+                debug!("unable to send event to WebSocket client: {:?}", err);
+                break;
+            }
+        }
+    }
+
+    async fn send_event(
+        tx: &mut SplitSink<WebSocket, Message>,
+        event: &StateEvent,
+    ) -> anyhow::Result<()> {
+        let json = serde_json::to_string(event)?;
+        tx.send(Message::text(json)).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_master_brightness(
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let value = state.lock().await.master_brightness();
+
+        Ok(warp::reply::json(&MasterBrightness { value }))
+    }
+
+    pub(crate) async fn post_master_brightness(
+        state: Arc<Mutex<Runtime>>,
+        request: MasterBrightness,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let res = state.lock().await.set_master_brightness(request.value);
+        debug!("runtime::set_master_brightness returned {:?}", res);
+
+        match res {
+            Ok(()) => Ok(http::StatusCode::OK),
+            Err(err) => Ok(anyhow_error_status(&err, http::StatusCode::BAD_REQUEST)),
+        }
+    }
+
+    pub(crate) async fn get_stats(
+        state: Arc<Mutex<Runtime>>,
+        stats: Arc<Mutex<TickStats>>,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let fixtures = state.lock().await.num_fixtures();
+        let tick_loop = stats.lock().await.clone();
+
+        Ok(warp::reply::json(&Stats {
+            tick_loop,
+            fixtures,
+        }))
+    }
+
+    pub(crate) async fn get_fixtures_root(
+        query: FixturesQuery,
+        state: Arc<Mutex<Runtime>>,
+        universe: Arc<UniverseConfig>,
+    ) -> Result<Box<dyn warp::Reply>, Infallible> {
+        if let Some(tag) = query.tag {
+            // Filtered metadata is not cached.
+            let metadata = state.lock().await.metadata(universe.as_ref(), Some(&tag));
+            return Ok(Box::new(warp::reply::json(&metadata)));
+        }
+
+        let res = state.lock().await.metadata_json(universe.as_ref());
+        match res {
+            Ok(json) => Ok(Box::new(warp::reply::with_header(
+                json.as_ref().clone(),
+                "content-type",
+                "application/json",
+            ))),
+            Err(err) => {
+                warn!("unable to build metadata: {:?}", err);
+                Ok(Box::new(http::StatusCode::INTERNAL_SERVER_ERROR))
+            }
+        }
     }
 
     pub(crate) async fn get_fixtures_fixture_root(
@@ -289,23 +1432,133 @@ mod handlers {
         state: Arc<Mutex<Runtime>>,
         universe: Arc<UniverseConfig>,
     ) -> Result<impl warp::Reply, Rejection> {
-        if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
-            Ok(warp::reply::json(
-                &fixture.alloy_metadata(universe.as_ref()),
-            ))
+        if let Some(metadata) = state
+            .lock()
+            .await
+            .fixture_metadata(&fixture_name, universe.as_ref())
+        {
+            Ok(warp::reply::json(&metadata))
         } else {
             Err(warp::reject::not_found())
         }
     }
 
+    pub(crate) async fn delete_fixtures_fixture(
+        fixture_name: String,
+        query: RemoveFixtureQuery,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Rejection> {
+        let res = state
+            .lock()
+            .await
+            .remove_fixture(&fixture_name, query.blackout);
+        debug!("runtime::remove_fixture returned {:?}", res);
+        match res {
+            Ok(_) => Ok(http::StatusCode::OK),
+            Err(err) => Ok(error_status(&err)),
+        }
+    }
+
     pub(crate) async fn get_fixtures_fixture_programs_root(
         fixture_name: String,
         state: Arc<Mutex<Runtime>>,
         universe: Arc<UniverseConfig>,
+    ) -> Result<impl warp::Reply, Rejection> {
+        if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
+            Ok(warp::reply::json(&fixture.metadata(universe.as_ref())))
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
+    pub(crate) async fn post_programs_set_active(
+        state: Arc<Mutex<Runtime>>,
+        request: SetActiveProgramsRequest,
+    ) -> Result<Box<dyn warp::Reply>, Infallible> {
+        let fixtures = match &request.fixtures {
+            FixtureSelection::Named(names) => Some(names.as_slice()),
+            FixtureSelection::Wildcard(w) if w == "*" => None,
+            FixtureSelection::Wildcard(_) => {
+                return Ok(Box::new(http::StatusCode::BAD_REQUEST));
+            }
+        };
+
+        let res = state
+            .lock()
+            .await
+            .set_active_program_many(&request.program, fixtures);
+        debug!("runtime::set_active_program_many returned {:?}", res);
+
+        Ok(Box::new(warp::reply::json(&batch_results(res))))
+    }
+
+    /// The outcome of setting the active program of all fixtures with a tag.
+    #[derive(Serialize)]
+    pub(crate) struct TagSetProgramResult {
+        /// Fixtures which switched to the program.
+        switched: Vec<String>,
+        /// Fixtures which don't have the program and kept their current program.
+        skipped: Vec<String>,
+    }
+
+    pub(crate) async fn post_tags_tag_set_program(
+        tag: String,
+        program_name: String,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Rejection> {
+        let mut state = state.lock().await;
+
+        let fixtures = state.fixtures_with_tag(&tag);
+        if fixtures.is_empty() {
+            return Err(warp::reject::not_found());
+        }
+
+        let res = state.set_active_program_many(&program_name, Some(&fixtures));
+        debug!("runtime::set_active_program_many returned {:?}", res);
+
+        let (switched, skipped): (Vec<_>, Vec<_>) = res.into_iter().partition(|(_, r)| r.is_ok());
+        Ok(warp::reply::json(&TagSetProgramResult {
+            switched: switched.into_iter().map(|(name, _)| name).collect(),
+            skipped: skipped.into_iter().map(|(name, _)| name).collect(),
+        }))
+    }
+
+    pub(crate) async fn post_fixtures_fixture_enabled(
+        fixture_name: String,
+        state: Arc<Mutex<Runtime>>,
+        enabled: bool,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let res = state
+            .lock()
+            .await
+            .set_fixture_enabled(&fixture_name, enabled);
+        debug!("runtime::set_fixture_enabled returned {:?}", res);
+
+        Ok(match res {
+            Ok(()) => http::StatusCode::OK,
+            Err(err) => error_status(&err),
+        })
+    }
+
+    pub(crate) async fn get_fixtures_fixture_outputs(
+        fixture_name: String,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Rejection> {
+        if let Some(outputs) = state.lock().await.fixture_outputs(&fixture_name) {
+            Ok(warp::reply::json(&outputs))
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
+    pub(crate) async fn get_fixtures_fixture_changes(
+        fixture_name: String,
+        query: ChangesQuery,
+        state: Arc<Mutex<Runtime>>,
     ) -> Result<impl warp::Reply, Rejection> {
         if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
             Ok(warp::reply::json(
-                &fixture.alloy_metadata(universe.as_ref()),
+                &fixture.parameter_changes_since(query.since),
             ))
         } else {
             Err(warp::reject::not_found())
@@ -315,6 +1568,7 @@ mod handlers {
     pub(crate) async fn post_fixtures_fixture_set_program(
         fixture_name: String,
         program_name: String,
+        query: ProgramFadeQuery,
         state: Arc<Mutex<Runtime>>,
     ) -> Result<impl warp::Reply, Rejection> {
         let mut state = state.lock().await;
@@ -322,10 +1576,16 @@ mod handlers {
         if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
             let res = fixture.set_active_program(&program_name);
             debug!("fixture::set_active_program returned {:?}", res);
-            // TODO figure out proper errors
             match res {
-                Ok(_) => Ok(http::StatusCode::OK),
-                Err(_) => Ok(http::StatusCode::NOT_FOUND),
+                Ok(_) => {
+                    if let Some(fade_ms) = query.fade_ms {
+                        state
+                            .set_next_program_fade(&fixture_name, Duration::from_millis(fade_ms))
+                            .expect("fixture disappeared");
+                    }
+                    Ok(http::StatusCode::OK)
+                }
+                Err(err) => Ok(error_status(&err)),
             }
         } else {
             Err(warp::reject::not_found())
@@ -334,16 +1594,24 @@ mod handlers {
 
     pub(crate) async fn post_fixtures_fixture_cycle_program(
         fixture_name: String,
+        query: CycleProgramQuery,
         state: Arc<Mutex<Runtime>>,
     ) -> Result<impl warp::Reply, Rejection> {
         let mut state = state.lock().await;
 
         if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
-            let res = fixture.cycle_active_program();
+            let res = fixture.cycle_active_program(query.include_all);
             debug!("fixture::cycle_active_program returned {:?}", res);
             // TODO figure out proper errors
             match res {
-                Ok(new_program) => Ok(warp::reply::json(&new_program)),
+                Ok(new_program) => {
+                    if let Some(fade_ms) = query.fade_ms {
+                        state
+                            .set_next_program_fade(&fixture_name, Duration::from_millis(fade_ms))
+                            .expect("fixture disappeared");
+                    }
+                    Ok(warp::reply::json(&new_program))
+                }
                 Err(_) => Err(warp::reject::not_found()),
             }
         } else {
@@ -351,6 +1619,61 @@ mod handlers {
         }
     }
 
+    pub(crate) async fn get_fixtures_fixture_export(
+        fixture_name: String,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Rejection> {
+        if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
+            Ok(warp::reply::json(&fixture.export_state()))
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
+    pub(crate) async fn post_fixtures_fixture_import(
+        fixture_name: String,
+        state: Arc<Mutex<Runtime>>,
+        fixture_state: FixtureState,
+    ) -> Result<impl warp::Reply, Rejection> {
+        let mut state = state.lock().await;
+
+        if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
+            let res = fixture.import_state(fixture_state);
+            debug!("fixture::import_state returned {:?}", res);
+            match res {
+                Ok(_) => Ok(http::StatusCode::OK),
+                Err(err) => Ok(anyhow_error_status(&err, http::StatusCode::BAD_REQUEST)),
+            }
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
+    pub(crate) async fn post_fixtures_fixture_reload(
+        fixture_name: String,
+        state: Arc<Mutex<Runtime>>,
+        universe: Arc<UniverseConfig>,
+    ) -> Result<Box<dyn warp::Reply>, Rejection> {
+        if state.lock().await.get_fixture(&fixture_name).is_none() {
+            return Err(warp::reject::not_found());
+        }
+
+        // This only locks the runtime briefly, the fixture is loaded without holding the lock.
+        let res = Runtime::reload_fixture(&state, &fixture_name, universe).await;
+        debug!("runtime::reload_fixture returned {:?}", res);
+        match res {
+            Ok(_) => Ok(Box::new(http::StatusCode::OK)),
+            Err(err) => {
+                warn!("unable to reload fixture {}: {:?}", fixture_name, err);
+                // Report the full error chain, which includes Lua errors with line numbers.
+                Ok(Box::new(warp::reply::with_status(
+                    format!("{:#}", err),
+                    anyhow_error_status(&err, http::StatusCode::INTERNAL_SERVER_ERROR),
+                )))
+            }
+        }
+    }
+
     pub(crate) async fn get_fixtures_fixture_programs_program_root(
         fixture_name: String,
         program_name: String,
@@ -358,7 +1681,7 @@ mod handlers {
     ) -> Result<impl warp::Reply, Rejection> {
         if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
             if let Some(program) = fixture.get_program(&program_name) {
-                Ok(warp::reply::json(&program.alloy_metadata()))
+                Ok(warp::reply::json(&program.metadata()))
             } else {
                 Err(warp::reject::not_found())
             }
@@ -367,6 +1690,29 @@ mod handlers {
         }
     }
 
+    pub(crate) async fn post_fixtures_fixture_programs_program_root(
+        fixture_name: String,
+        program_name: String,
+        state: Arc<Mutex<Runtime>>,
+        metadata: ProgramParametersRequest,
+    ) -> Result<impl warp::Reply, Rejection> {
+        let mut state = state.lock().await;
+
+        if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
+            if fixture.get_program(&program_name).is_none() {
+                return Err(warp::reject::not_found());
+            }
+            let res = fixture.set_program_parameters(&program_name, metadata);
+            debug!("fixture::set_program_parameters returned {:?}", res);
+            match res {
+                Ok(_) => Ok(http::StatusCode::OK),
+                Err(err) => Ok(anyhow_error_status(&err, http::StatusCode::BAD_REQUEST)),
+            }
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
     pub(crate) async fn get_fixtures_fixture_programs_program_parameters_root(
         fixture_name: String,
         program_name: String,
@@ -374,7 +1720,7 @@ mod handlers {
     ) -> Result<impl warp::Reply, Rejection> {
         if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
             if let Some(program) = fixture.get_program(&program_name) {
-                Ok(warp::reply::json(&program.alloy_metadata()))
+                Ok(warp::reply::json(&program.metadata()))
             } else {
                 Err(warp::reject::not_found())
             }
@@ -392,7 +1738,7 @@ mod handlers {
         if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
             if let Some(program) = fixture.get_program(&program_name) {
                 if let Some(parameter) = program.get_parameter(&parameter_name) {
-                    Ok(warp::reply::json(&parameter.alloy_metadata()))
+                    Ok(warp::reply::json(&parameter.metadata()))
                 } else {
                     Err(warp::reject::not_found())
                 }
@@ -404,25 +1750,55 @@ mod handlers {
         }
     }
 
+    pub(crate) async fn post_fixtures_fixture_programs_program_parameters_root(
+        fixture_name: String,
+        program_name: String,
+        state: Arc<Mutex<Runtime>>,
+        set_requests: BTreeMap<String, ParameterRequest>,
+    ) -> Result<impl warp::Reply, Rejection> {
+        let mut state = state.lock().await;
+
+        if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
+            if let Some(program) = fixture.get_program_mut(&program_name) {
+                let before = program.parameter_values();
+                let res = program.set_parameters(set_requests);
+                debug!("program::set_parameters returned {:?}", res);
+                let program_name = program.name().to_string();
+                let after = program.parameter_values();
+                fixture.record_parameter_changes(&program_name, &before, after);
+                Ok(warp::reply::json(&batch_results(res)))
+            } else {
+                Err(warp::reject::not_found())
+            }
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
     pub(crate) async fn post_fixtures_fixture_programs_program_parameters_parameter(
         fixture_name: String,
         program_name: String,
         parameter_name: String,
         state: Arc<Mutex<Runtime>>,
-        set_request: ParameterSetRequest,
-    ) -> Result<impl warp::Reply, Rejection> {
+        set_request: ParameterRequest,
+    ) -> Result<Box<dyn warp::Reply>, Rejection> {
         let mut state = state.lock().await;
 
         if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
             if let Some(program) = fixture.get_program_mut(&program_name) {
+                let before = program.parameter_values();
                 if let Some(parameter) = program.get_parameter_mut(&parameter_name) {
                     let res = parameter.set(set_request);
                     debug!("parameter::set returned {:?}", res);
-                    // TODO figure out proper errors
-                    match res {
-                        Ok(_) => Ok(http::StatusCode::OK),
-                        Err(_) => Ok(http::StatusCode::BAD_REQUEST),
-                    }
+                    // Return the stored state, so clients don't need another round trip.
+                    let reply: Box<dyn warp::Reply> = match res {
+                        Ok(_) => Box::new(warp::reply::json(&parameter.metadata())),
+                        Err(err) => Box::new(error_status(&err)),
+                    };
+                    let program_name = program.name().to_string();
+                    let after = program.parameter_values();
+                    fixture.record_parameter_changes(&program_name, &before, after);
+                    Ok(reply)
                 } else {
                     Err(warp::reject::not_found())
                 }
@@ -438,20 +1814,25 @@ mod handlers {
         fixture_name: String,
         program_name: String,
         parameter_name: String,
+        query: CycleParameterQuery,
         state: Arc<Mutex<Runtime>>,
-    ) -> Result<impl warp::Reply, Rejection> {
+    ) -> Result<Box<dyn warp::Reply>, Rejection> {
         let mut state = state.lock().await;
 
         if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
             if let Some(program) = fixture.get_program_mut(&program_name) {
+                let before = program.parameter_values();
                 if let Some(parameter) = program.get_parameter_mut(&parameter_name) {
-                    let res = parameter.cycle();
+                    let res = parameter.cycle(query.dir == CycleDirection::Back);
                     debug!("parameter::cycle returned {:?}", res);
-                    // TODO figure out proper errors
-                    match res {
-                        Ok(new_level) => Ok(warp::reply::json(&new_level)),
-                        Err(_) => Err(warp::reject::not_found()),
-                    }
+                    let reply: Box<dyn warp::Reply> = match res {
+                        Ok(new_level) => Box::new(warp::reply::json(&new_level)),
+                        Err(err) => Box::new(error_status(&err)),
+                    };
+                    let program_name = program.name().to_string();
+                    let after = program.parameter_values();
+                    fixture.record_parameter_changes(&program_name, &before, after);
+                    Ok(reply)
                 } else {
                     Err(warp::reject::not_found())
                 }
@@ -462,4 +1843,224 @@ mod handlers {
             Err(warp::reject::not_found())
         }
     }
+
+    pub(crate) async fn post_fixtures_fixture_programs_program_parameters_parameter_increment(
+        fixture_name: String,
+        program_name: String,
+        parameter_name: String,
+        state: Arc<Mutex<Runtime>>,
+        request: IncrementParameterRequest,
+    ) -> Result<Box<dyn warp::Reply>, Rejection> {
+        let mut state = state.lock().await;
+
+        if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
+            if let Some(program) = fixture.get_program_mut(&program_name) {
+                let before = program.parameter_values();
+                if let Some(parameter) = program.get_parameter_mut(&parameter_name) {
+                    let res = parameter.increment(request.delta);
+                    debug!("parameter::increment returned {:?}", res);
+                    let reply: Box<dyn warp::Reply> = match res {
+                        Ok(new_value) => Box::new(warp::reply::json(&new_value)),
+                        Err(err) => Box::new(error_status(&err)),
+                    };
+                    let program_name = program.name().to_string();
+                    let after = program.parameter_values();
+                    fixture.record_parameter_changes(&program_name, &before, after);
+                    Ok(reply)
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            } else {
+                Err(warp::reject::not_found())
+            }
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
+    pub(crate) async fn post_fixtures_fixture_programs_program_reload(
+        fixture_name: String,
+        program_name: String,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<Box<dyn warp::Reply>, Infallible> {
+        let res = state
+            .lock()
+            .await
+            .reload_program(&fixture_name, &program_name);
+        debug!("runtime::reload_program returned {:?}", res);
+        match res {
+            Ok(_) => Ok(Box::new(http::StatusCode::OK)),
+            Err(err) => {
+                warn!(
+                    "unable to reload program {} of fixture {}: {:?}",
+                    program_name, fixture_name, err
+                );
+                // Report the full error chain, which includes Lua errors with line numbers.
+                Ok(Box::new(warp::reply::with_status(
+                    format!("{:#}", err),
+                    anyhow_error_status(&err, http::StatusCode::INTERNAL_SERVER_ERROR),
+                )))
+            }
+        }
+    }
+
+    pub(crate) async fn post_fixtures_fixture_programs_program_reset_parameters(
+        fixture_name: String,
+        program_name: String,
+        state: Arc<Mutex<Runtime>>,
+    ) -> Result<impl warp::Reply, Rejection> {
+        let mut state = state.lock().await;
+
+        if let Some(fixture) = state.get_fixture_mut(&fixture_name) {
+            if let Some(program) = fixture.get_program_mut(&program_name) {
+                let before = program.parameter_values();
+                program.reset_parameters();
+                let program_name = program.name().to_string();
+                let after = program.parameter_values();
+                let metadata = program.metadata();
+                fixture.record_parameter_changes(&program_name, &before, after);
+                Ok(warp::reply::json(&metadata))
+            } else {
+                Err(warp::reject::not_found())
+            }
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+
+    pub(crate) async fn post_fixtures_fixture_programs_program_preview(
+        fixture_name: String,
+        program_name: String,
+        state: Arc<Mutex<Runtime>>,
+        request: PreviewProgramRequest,
+    ) -> Result<Box<dyn warp::Reply>, Infallible> {
+        if !(0.0..=u32::MAX as f64).contains(&request.at) {
+            return Ok(Box::new(http::StatusCode::BAD_REQUEST));
+        }
+
+        let res = state.lock().await.preview_program(
+            &fixture_name,
+            &program_name,
+            request.parameters,
+            Duration::from_secs_f64(request.at),
+        );
+        debug!("runtime::preview_program returned {:?}", res);
+        match res {
+            Ok(set_requests) => Ok(Box::new(warp::reply::json(&set_requests))),
+            Err(err) => Ok(Box::new(anyhow_error_status(
+                &err,
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+            ))),
+        }
+    }
+
+    pub(crate) async fn get_fixtures_fixture_programs_program_debug(
+        fixture_name: String,
+        program_name: String,
+        state: Arc<Mutex<Runtime>>,
+        debug_api: bool,
+    ) -> Result<Box<dyn warp::Reply>, Rejection> {
+        if !debug_api {
+            return Ok(Box::new(http::StatusCode::FORBIDDEN));
+        }
+
+        if let Some(fixture) = state.lock().await.get_fixture(&fixture_name) {
+            if let Some(program) = fixture.get_program(&program_name) {
+                let res = program.debug_globals();
+                debug!("program::debug_globals returned {:?}", res);
+                match res {
+                    Ok(globals) => Ok(Box::new(warp::reply::json(&globals))),
+                    Err(_) => Ok(Box::new(http::StatusCode::INTERNAL_SERVER_ERROR)),
+                }
+            } else {
+                Err(warp::reject::not_found())
+            }
+        } else {
+            Err(warp::reject::not_found())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_util;
+    use alloy::api::{SetRequest, SetRequestTarget};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use warp::Reply;
+
+    #[tokio::test]
+    async fn health_endpoint_responds_before_fixtures_are_loaded() {
+        let listener = bind(([127, 0, 0, 1], 0).into()).await.unwrap();
+        let url = format!("http://{}/healthz", listener.local_addr().unwrap());
+        let (readiness_tx, readiness_rx) = watch::channel(Readiness::LoadingFixtures);
+        let health_server = tokio::spawn(run_health_server(listener, readiness_rx));
+
+        let resp = reqwest::get(&url).await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(resp.text().await.unwrap(), "initializing: loading fixtures");
+
+        // The health server hands over once the process is ready.
+        readiness_tx.send_replace(Readiness::Ready);
+        health_server.await.unwrap().unwrap();
+    }
+
+    /// Force-ticks the runtime and returns the status and the returned set requests, if any.
+    async fn force_tick(
+        runtime: &Arc<Mutex<Runtime>>,
+        submarine_base_url: &Url,
+    ) -> (warp::http::StatusCode, Vec<SetRequest>) {
+        let resp = handlers::post_tick(
+            runtime.clone(),
+            submarine_base_url.clone(),
+            reqwest::Client::new(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let status = resp.status();
+        let body = warp::hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let set_requests = if status.is_success() {
+            serde_json::from_slice(&body).unwrap()
+        } else {
+            Vec::new()
+        };
+        (status, set_requests)
+    }
+
+    #[tokio::test]
+    async fn force_tick_runs_a_single_tick_while_paused() {
+        let (_dir, runtime) = test_util::runtime_with_program(
+            r#"function setup()
+end
+
+n = 0
+function tick(now)
+    n = n + 1
+    set_alias("a", n)
+end
+"#,
+            test_util::options(),
+        );
+        let runtime = Arc::new(Mutex::new(runtime));
+        let posts = Arc::new(AtomicUsize::new(0));
+        let submarine_base_url = crate::tests::fake_submarine(posts.clone());
+
+        let (status, _) = force_tick(&runtime, &submarine_base_url).await;
+        assert_eq!(status, warp::http::StatusCode::CONFLICT);
+        assert_eq!(posts.load(Ordering::SeqCst), 0);
+
+        runtime.lock().await.set_paused(true);
+        for n in 1..=2 {
+            let (status, set_requests) = force_tick(&runtime, &submarine_base_url).await;
+            assert_eq!(status, warp::http::StatusCode::OK);
+            assert_eq!(set_requests.len(), 1);
+            assert!(matches!(
+                set_requests[0].target,
+                SetRequestTarget::Address(1)
+            ));
+            assert_eq!(set_requests[0].value, n);
+            assert_eq!(posts.load(Ordering::SeqCst), n as usize);
+        }
+    }
 }
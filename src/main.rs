@@ -1,20 +1,37 @@
-use alloy::api::SetRequest;
+// The API is one deeply nested warp filter.
+#![recursion_limit = "256"]
+
+use alloy::api::{SetRequest, SetRequestTarget};
 use alloy::config::UniverseConfig;
+use alloy::{Address, LOW};
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::batch::SetRequestBatch;
 use crate::config::Config;
-use anyhow::Context;
+use crate::connection::ConnectionHealth;
+use crate::http::Readiness;
+use crate::runtime::runtime::{Runtime, RuntimeOptions};
+use crate::stats::TickStats;
+use anyhow::{bail, Context};
+use arc_swap::ArcSwap;
+use chrono::Local;
 use flexi_logger::{DeferredNow, Logger, LoggerHandle, TS_DASHES_BLANK_COLONS_DOT_BLANK};
-use log::{debug, info, warn, Record};
+use log::{debug, error, info, warn, Record};
 use reqwest::Url;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use tokio::task;
 
+mod batch;
 mod config;
+mod connection;
 mod http;
 mod prom;
 mod runtime;
+mod scaffold;
+mod stats;
+mod watcher;
 
 pub(crate) type Result<T> = anyhow::Result<T>;
 
@@ -46,25 +63,116 @@ pub fn set_up_logging() -> std::result::Result<LoggerHandle, Box<dyn std::error:
     Ok(handle)
 }
 
+/// Sets up a tracing subscriber which writes spans to ./tracing.folded, for use with inferno or
+/// other flamegraph tools.
+/// Logging is not affected by this, log records are still handled by the logger.
+#[cfg(feature = "tracing")]
+fn set_up_tracing() -> Result<tracing_flame::FlushGuard<std::io::BufWriter<std::fs::File>>> {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let (flame_layer, guard) = tracing_flame::FlameLayer::with_file("./tracing.folded")
+        .map_err(|e| anyhow::anyhow!("unable to create tracing output file: {}", e))?;
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(flame_layer))
+        .context("unable to set tracing subscriber")?;
+
+    Ok(guard)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     set_up_logging().unwrap();
+    // Flushes traces when dropped, i.e., when main returns.
+    #[cfg(feature = "tracing")]
+    let _flame_guard = set_up_tracing()?;
 
     info!("reading config file...");
     let cfg = Config::read_from_file("config.yaml").context("unable to read config file")?;
     debug!("read config {:?}", cfg);
 
+    // Handle subcommands.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => {}
+        Some("new-fixture") => {
+            let name = args
+                .get(1)
+                .context("usage: kaleidoscope new-fixture <name>")?;
+            // New fixtures go into the first fixtures directory.
+            let fixtures_paths = cfg.fixtures_path.paths();
+            let fixtures_root = fixtures_paths
+                .first()
+                .context("no fixtures path configured")?;
+            return scaffold::new_fixture(fixtures_root, name)
+                .context("unable to scaffold fixture");
+        }
+        Some("check") | Some("--check") => {
+            // Validate fixtures without starting the tick loop, e.g., before deploying.
+            let universe_config = match args.get(1) {
+                Some(path) => read_universe_config_file(path)
+                    .context(format!("unable to read universe config from {:?}", path))?,
+                None => {
+                    let submarine_base_url = Url::parse(&cfg.submarine_http_url)
+                        .context("unable to parse submarine base URL")?;
+                    load_universe_config(&cfg, &submarine_base_url, &reqwest::Client::new())
+                        .await?
+                        .0
+                }
+            };
+            let problems = Runtime::check(
+                &cfg.fixtures_path.paths(),
+                &universe_config,
+                cfg.allow_output_overlap,
+                &cfg.alias_overrides,
+            )?;
+            if problems > 0 {
+                bail!("found {} problems with fixtures", problems)
+            }
+            info!("all fixtures loaded successfully");
+            return Ok(());
+        }
+        Some(other) => bail!("unknown subcommand: {}", other),
+    }
+
+    // Bring up Prometheus and a health endpoint first, so that the process can be observed
+    // while it connects to Submarine and loads fixtures, which may be slow.
+    info!("setting up prometheus...");
+    let prom_listen_address = cfg
+        .prometheus_listen_address
+        .parse()
+        .context("unable to parse prometheus listen address")?;
+    prom::start_prometheus(prom_listen_address).context("unable to start prometheus")?;
+
+    info!("starting HTTP health endpoint...");
+    let http_server_address = cfg.http_listen_address.parse()?;
+    let http_listener = http::bind(http_server_address)
+        .await
+        .context("unable to start HTTP server")?;
+    let (readiness_tx, readiness_rx) = watch::channel(Readiness::ConnectingToSubmarine);
+    let health_server = task::spawn(http::run_health_server(
+        http_listener.clone(),
+        readiness_rx.clone(),
+    ));
+    info!(
+        "HTTP health endpoint is listening on http://{}/healthz",
+        http_server_address
+    );
+
     info!("connecting to Submarine...");
     let submarine_base_url =
         Url::parse(&cfg.submarine_http_url).context("unable to parse submarine base URL")?;
     let submarine_client = reqwest::ClientBuilder::default()
         .build()
         .expect("unable to build HTTP client");
-    let universe_config = get_universe_config(&submarine_base_url, &submarine_client)
-        .await
-        .context("unable to get universe config from submarine")?;
+    let (universe_config, offline) =
+        load_universe_config(&cfg, &submarine_base_url, &submarine_client).await?;
     debug!("got universe config {:?}", universe_config);
 
+    if let Some(amqp_server_address) = &cfg.amqp_server_address {
+        warn!(
+            "event input is currently disabled, ignoring AMQP broker at {}",
+            amqp_server_address
+        );
+    }
     /*
     info!("connecting to AMQP broker...");
     let amqp_client =
@@ -74,30 +182,180 @@ async fn main() -> Result<()> {
     debug!("connected with client {:?}", amqp_client);
      */
 
-    info!("setting up prometheus...");
-    let prom_listen_address = cfg
-        .prometheus_listen_address
-        .parse()
-        .context("unable to parse prometheus listen address")?;
-    prom::start_prometheus(prom_listen_address).context("unable to start prometheus")?;
-
     info!("setting up runtime...");
-    let runtime = runtime::runtime::Runtime::new(&cfg.fixtures_path, &universe_config)
-        .context("unable to set up runtime")?;
+    readiness_tx.send_replace(Readiness::LoadingFixtures);
+    let runtime = Runtime::new(
+        &cfg.fixtures_path.paths(),
+        &universe_config,
+        RuntimeOptions {
+            warning_interval: Duration::from_millis(cfg.warning_interval_ms),
+            startup_fade: Duration::from_millis(cfg.startup_fade_ms),
+            program_fade: Duration::from_millis(cfg.program_fade_ms),
+            suppress_unchanged: cfg.suppress_unchanged_outputs,
+            send_changed_only: cfg.send_changed_outputs_only,
+            parallel_ticks: cfg.parallel_ticks,
+            state_file: cfg.state_file.clone().map(Into::into),
+            allow_output_overlap: cfg.allow_output_overlap,
+            strict_outputs: cfg.strict_program_outputs,
+            alias_overrides: cfg.alias_overrides.clone(),
+            location: cfg.location,
+        },
+    )
+    .context("unable to set up runtime")?;
     let runtime = Arc::new(Mutex::new(runtime));
+    let universe_config = Arc::new(ArcSwap::from_pointee(universe_config));
+
+    if cfg.watch_fixtures {
+        if runtime.lock().await.is_bundled() {
+            warn!("fixtures were loaded from a bundle, not watching for changes");
+        } else {
+            let watch = watcher::watch_fixtures(
+                cfg.fixtures_path.paths(),
+                runtime.clone(),
+                universe_config.clone(),
+            );
+            task::spawn(async move {
+                if let Err(err) = watch.await {
+                    warn!("unable to watch fixtures for changes: {:?}", err)
+                }
+            });
+        }
+    }
+
+    #[cfg(unix)]
+    if let (true, Some(path)) = (offline, cfg.universe_config_file.clone()) {
+        let reload = reload_universe_on_sighup(path, runtime.clone(), universe_config.clone());
+        task::spawn(async move {
+            if let Err(err) = reload.await {
+                warn!("unable to reload the universe config on SIGHUP: {:?}", err)
+            }
+        });
+    }
+
+    // Hand the listener over from the health endpoint to the full API.
+    readiness_tx.send_replace(Readiness::Ready);
+    health_server
+        .await
+        .context("health endpoint panicked")?
+        .context("health endpoint failed")?;
+
+    if cfg.state_file.is_some() {
+        task::spawn(save_state_loop(runtime.clone()));
+    }
+
+    let stats = Arc::new(Mutex::new(TickStats::default()));
 
     info!("starting HTTP server...");
-    let http_server_address = cfg.http_listen_address.parse()?;
-    let _http_server = task::spawn(http::run_server(
-        http_server_address,
+    let http_server = task::spawn(http::run_server(
+        http_listener,
         runtime.clone(),
-        Arc::new(universe_config),
+        stats.clone(),
+        universe_config.clone(),
+        cfg.debug_api,
+        readiness_rx,
+        submarine_base_url.clone(),
+        submarine_client.clone(),
     ));
     info!("HTTP server is listening on http://{}", http_server_address);
 
     info!("starting tick loop");
-    let mut print_ticker = tokio::time::interval(Duration::from_secs(2));
-    let mut tick_ticker = tokio::time::interval(Duration::from_millis(5));
+    let blackout_on_shutdown = cfg.blackout_on_shutdown;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut tick_loop = task::spawn(tick_loop(
+        cfg,
+        runtime.clone(),
+        stats,
+        submarine_base_url.clone(),
+        submarine_client.clone(),
+        shutdown_rx,
+    ));
+
+    supervise(http_server, &mut tick_loop).await?;
+
+    // Let the tick loop finish its current iteration, so that we don't abort while posting set
+    // requests, and so that it doesn't overwrite the blackout.
+    shutdown_tx.send_replace(true);
+    tick_loop
+        .await
+        .context("tick loop panicked")?
+        .context("tick loop failed")?;
+
+    // Changes made since the last periodic save would otherwise be lost.
+    if let Err(err) = runtime.lock().await.save_state_if_changed() {
+        warn!("unable to save state: {:?}", err);
+    }
+
+    if blackout_on_shutdown {
+        info!("setting all outputs to LOW...");
+        let set_requests = blackout_set_requests(&universe_config.load());
+        match tokio::time::timeout(
+            SHUTDOWN_BLACKOUT_TIMEOUT,
+            post_set_requests(&submarine_base_url, &submarine_client, &set_requests),
+        )
+        .await
+        {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => warn!("unable to set outputs to LOW: {:?}", err),
+            Err(_) => warn!("timed out setting outputs to LOW"),
+        }
+    }
+
+    info!("shut down");
+    Ok(())
+}
+
+/// Maximum time to wait for Submarine to accept the blackout on shutdown.
+const SHUTDOWN_BLACKOUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Builds set requests which set every output of the universe to LOW.
+fn blackout_set_requests(universe_config: &UniverseConfig) -> Vec<SetRequest> {
+    universe_config
+        .devices
+        .iter()
+        .flat_map(|d| &d.outputs)
+        .map(|o| SetRequest {
+            value: LOW,
+            target: SetRequestTarget::Address(o.address),
+        })
+        .collect()
+}
+
+/// Waits for Ctrl-C, or for the HTTP server or the tick loop to exit.
+/// Neither of these should ever exit. If one of them does, this returns an error, which shuts down
+/// the whole process instead of running in a degraded state.
+async fn supervise(
+    http_server: task::JoinHandle<Result<()>>,
+    tick_loop: &mut task::JoinHandle<Result<()>>,
+) -> Result<()> {
+    tokio::select! {
+        res = http_server => {
+            error!("HTTP server exited unexpectedly: {:?}", res);
+            bail!("HTTP server exited")
+        }
+        res = tick_loop => {
+            error!("tick loop exited unexpectedly: {:?}", res);
+            bail!("tick loop exited")
+        }
+        res = tokio::signal::ctrl_c() => {
+            res.context("unable to listen for Ctrl-C")?;
+            info!("received Ctrl-C, shutting down...");
+        }
+    }
+
+    Ok(())
+}
+
+async fn tick_loop(
+    cfg: Config,
+    runtime: Arc<Mutex<Runtime>>,
+    stats: Arc<Mutex<TickStats>>,
+    submarine_base_url: Url,
+    submarine_client: reqwest::Client,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    let tick_interval = Duration::from_millis(cfg.tick_interval_ms);
+    let mut print_ticker = tokio::time::interval(Duration::from_secs(cfg.stats_interval_secs));
+    let mut tick_ticker = tokio::time::interval(tick_interval);
     // First tick is free :o
     let mut last_print = print_ticker.tick().await;
     tick_ticker.tick().await;
@@ -105,18 +363,44 @@ async fn main() -> Result<()> {
     let mut send_time_avg = 0.0;
     let mut tick_time_avg = 0.0;
     let mut i = 1_u64;
+    let mut j = 1_u64;
+    // Number of ticks which overran the tick interval since statistics were last logged.
+    let mut overruns = 0_u64;
     let mut set_requests = Vec::new();
+    let mut batch = SetRequestBatch::new(Duration::from_millis(cfg.send_window_ms));
+    let mut health = ConnectionHealth::new(cfg.submarine_retry_threshold);
     loop {
         tokio::select! {
+            _ = shutdown.changed() => {
+                info!("tick loop shutting down");
+                return Ok(())
+            },
             tick = print_ticker.tick() => {
                 let dur = tick.duration_since(last_print).as_secs_f64();
 
+                let ticks_per_second = i as f64 / dur;
                 info!(
                     "avg tick: {:6.2}µs, send: {:6.2}µs, processed {:5} ticks/s",
-                    tick_time_avg, send_time_avg,  (i as f64 / dur) as u64
+                    tick_time_avg, send_time_avg, ticks_per_second as u64
                 );
+                {
+                    let mut stats = stats.lock().await;
+                    stats.tick_duration_avg_micros = tick_time_avg;
+                    stats.send_duration_avg_micros = send_time_avg;
+                    stats.ticks_per_second = ticks_per_second;
+                    stats.overruns = overruns;
+                    stats.timestamp = Some(Local::now());
+                }
+                if overruns > 0 {
+                    warn!(
+                        "{} ticks took longer than the tick interval of {:?}, consider increasing tick_interval_ms",
+                        overruns, tick_interval
+                    );
+                    overruns = 0;
+                }
 
                 i = 1;
+                j = 1;
                 send_time_avg = 0.0;
                 tick_time_avg = 0.0;
                 last_print = tick;
@@ -127,6 +411,10 @@ async fn main() -> Result<()> {
                 set_requests.clear();
                 let tick_time_taken = {
                     let mut runtime = runtime.lock().await;
+                    if runtime.is_paused() {
+                        // Outputs hold their current values.
+                        continue
+                    }
                     let before = Instant::now();
                     let res = runtime.tick();
                     let time_taken = before.elapsed().as_micros() as f64;
@@ -136,32 +424,190 @@ async fn main() -> Result<()> {
                         }
                         Err(err) => {
                             warn!("tick failed: {:?}",err);
+                            stats.lock().await.record_error(&err);
                             continue
                         }
                     }
                     time_taken
                 };
 
-                // Send set requests to submarine.
+                debug!("inner tick duration: {}µs", tick_time_taken);
+                prom::TICK_DURATION.observe(tick_time_taken);
+                tick_time_avg += (tick_time_taken - tick_time_avg) / i as f64;
+                i += 1;
+
+                // Batch set requests, and send them to submarine once the window has passed.
+                batch.push(&set_requests);
                 let before = Instant::now();
-                if let Err(e) = post_set_requests(&submarine_base_url, &submarine_client, &set_requests).await {
-                    warn!("unable to post set requests to submarine: {:?}", e);
-                    continue
+                let send_time_taken = 'send: {
+                    // Keep ticking while Submarine is unreachable, but don't hammer it.
+                    // Failed requests are requeued and the batch keeps coalescing in the
+                    // meantime, so the latest values are sent once it's back.
+                    if !batch.is_due() || !health.should_attempt(before) {
+                        break 'send 0.0
+                    }
+                    batch.take(&mut set_requests);
+                    if set_requests.is_empty() && cfg.send_changed_outputs_only {
+                        // Nothing changed, don't bother Submarine.
+                        break 'send 0.0
+                    }
+
+                    if let Err(e) = post_set_requests(&submarine_base_url, &submarine_client, &set_requests).await {
+                        warn!("unable to post set requests to submarine: {:?}", e);
+                        stats.lock().await.record_error(&e);
+                        // Programs don't necessarily produce their values again, so keep them.
+                        batch.requeue(&set_requests);
+                        // Submarine might have lost all values, e.g., if it restarted.
+                        runtime.lock().await.resend_all_outputs();
+                        if health.record_failure(Instant::now()) {
+                            task::spawn(revalidate_submarine(submarine_base_url.clone(), submarine_client.clone()));
+                        }
+                        break 'send before.elapsed().as_micros() as f64
+                    }
+                    health.record_success();
+                    let send_time_taken = before.elapsed().as_micros() as f64;
+
+                    debug!("send duration: {}µs", send_time_taken);
+                    prom::SEND_DURATION.observe(send_time_taken);
+                    send_time_avg += (send_time_taken - send_time_avg) / j as f64;
+                    j += 1;
+                    send_time_taken
+                };
+
+                // The interval catches up on overruns by ticking in bursts, which causes jitter.
+                if tick_time_taken + send_time_taken > tick_interval.as_micros() as f64 {
+                    prom::TICK_OVERRUNS.inc();
+                    overruns += 1;
                 }
-                let send_time_taken = before.elapsed().as_micros() as f64;
+            },
+        }
+    }
+}
 
-                debug!("inner tick duration: {}µs, send duration: {}µs",tick_time_taken, send_time_taken);
+/// Periodically saves the state of all fixtures, if it changed.
+/// Saving periodically instead of on every change avoids writing the file for every step of,
+/// e.g., a fader being moved.
+async fn save_state_loop(runtime: Arc<Mutex<Runtime>>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        if let Err(err) = runtime.lock().await.save_state_if_changed() {
+            warn!("unable to save state: {:?}", err);
+        }
+    }
+}
 
-                prom::TICK_DURATION.observe(tick_time_taken);
-                prom::SEND_DURATION.observe(send_time_taken);
+/// Checks whether Submarine is reachable at all, after posting set requests failed repeatedly.
+async fn revalidate_submarine(submarine_base_url: Url, client: reqwest::Client) {
+    match get_universe_config(&submarine_base_url, &client).await {
+        Ok(_) => warn!(
+            "submarine at {} is reachable, but does not accept set requests",
+            submarine_base_url
+        ),
+        Err(err) => error!(
+            "submarine at {} is unreachable, will keep retrying: {:?}",
+            submarine_base_url, err
+        ),
+    }
+}
 
-                send_time_avg += (send_time_taken - send_time_avg) / i as f64;
-                tick_time_avg += (tick_time_taken - tick_time_avg) / i as f64;
+/// Gets the universe config from Submarine, or from the configured universe config file if
+/// Submarine is unreachable.
+/// In the latter case, posting set requests fails until Submarine becomes reachable, like after
+/// losing the connection, and the latest value of every output is sent once it is.
+/// Returns the universe config and whether it was read from the file.
+async fn load_universe_config(
+    cfg: &Config,
+    submarine_base_url: &Url,
+    client: &reqwest::Client,
+) -> Result<(UniverseConfig, bool)> {
+    let err = match get_universe_config(submarine_base_url, client).await {
+        Ok(universe_config) => return Ok((universe_config, false)),
+        Err(err) => err,
+    };
+    let path = match &cfg.universe_config_file {
+        Some(path) => path,
+        None => return Err(err),
+    };
 
-                i += 1;
-            },
+    warn!(
+        "{:#}, starting in offline mode with universe config from {:?}",
+        err, path
+    );
+    let universe_config = read_universe_config_file(path)
+        .context(format!("unable to read universe config from {:?}", path))?;
+
+    Ok((universe_config, true))
+}
+
+/// Re-reads the universe config file on every SIGHUP and reloads all fixtures against it.
+/// The previous universe config is kept if the file can not be read.
+#[cfg(unix)]
+async fn reload_universe_on_sighup(
+    path: String,
+    runtime: Arc<Mutex<Runtime>>,
+    universe_config: Arc<ArcSwap<UniverseConfig>>,
+) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup()).context("unable to listen for SIGHUP")?;
+    while hangups.recv().await.is_some() {
+        info!("received SIGHUP, reloading universe config from {:?}", path);
+        let new_config = match read_universe_config_file(&path) {
+            Ok(universe_config) => Arc::new(universe_config),
+            Err(err) => {
+                warn!(
+                    "unable to read universe config from {:?}, keeping the previous one: {:?}",
+                    path, err
+                );
+                continue;
+            }
+        };
+
+        let (added, removed) = address_changes(&universe_config.load(), &new_config);
+        info!(
+            "universe config reloaded, added addresses {:?}, removed addresses {:?}",
+            added, removed
+        );
+        universe_config.store(new_config.clone());
+
+        let failed = Runtime::reload_all_fixtures(&runtime, new_config).await;
+        if failed > 0 {
+            warn!(
+                "{} fixtures do not match the new universe config and keep running as before",
+                failed
+            );
         }
     }
+
+    bail!("SIGHUP stream ended")
+}
+
+/// Returns the addresses added to and removed from a universe, in ascending order.
+#[cfg(unix)]
+fn address_changes(old: &UniverseConfig, new: &UniverseConfig) -> (Vec<Address>, Vec<Address>) {
+    let addresses = |universe_config: &UniverseConfig| -> BTreeSet<Address> {
+        universe_config
+            .devices
+            .iter()
+            .flat_map(|device| device.outputs.iter().map(|output| output.address))
+            .collect()
+    };
+    let (old, new) = (addresses(old), addresses(new));
+
+    (
+        new.difference(&old).copied().collect(),
+        old.difference(&new).copied().collect(),
+    )
+}
+
+/// Reads a universe config from a file, in the same JSON format Submarine serves it in.
+fn read_universe_config_file(path: &str) -> Result<UniverseConfig> {
+    let contents = std::fs::read_to_string(path)?;
+    let universe_config =
+        serde_json::from_str(&contents).context("unable to decode universe config")?;
+
+    Ok(universe_config)
 }
 
 async fn get_universe_config(
@@ -182,6 +628,10 @@ async fn get_universe_config(
     Ok(resp)
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(num_requests = set_requests.len()))
+)]
 async fn post_set_requests(
     submarine_base_url: &Url,
     client: &reqwest::Client,
@@ -195,7 +645,89 @@ async fn post_set_requests(
         .json(set_requests)
         .send()
         .await
-        .context("unable to post set requests to submarine")?;
+        .context("unable to post set requests to submarine")?
+        .error_for_status()
+        .context("submarine rejected set requests")?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_util;
+    use futures::future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use warp::Filter;
+
+    /// Serves a fake Submarine which counts posts of set requests, and returns its base URL.
+    pub(crate) fn fake_submarine(posts: Arc<AtomicUsize>) -> Url {
+        let set = warp::post()
+            .and(warp::path!("api" / "v1" / "universe" / "set"))
+            .map(move || {
+                posts.fetch_add(1, Ordering::SeqCst);
+                warp::reply()
+            });
+        let (addr, server) = warp::serve(set).bind_ephemeral(([127, 0, 0, 1], 0));
+        task::spawn(server);
+
+        Url::parse(&format!("http://{}", addr)).unwrap()
+    }
+
+    fn config(submarine_base_url: &Url) -> Config {
+        serde_yaml::from_str(&format!(
+            r#"
+prometheus_listen_address: "127.0.0.1:0"
+http_listen_address: "127.0.0.1:0"
+submarine_http_url: "{}"
+fixtures_path: "./fixtures"
+tick_interval_ms: 1
+"#,
+            submarine_base_url
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn no_set_requests_are_posted_while_paused() {
+        let (_dir, mut runtime) = test_util::runtime_with_program(
+            &test_util::constant_program("a", alloy::HIGH),
+            test_util::options(),
+        );
+        runtime.set_paused(true);
+        let runtime = Arc::new(Mutex::new(runtime));
+
+        let posts = Arc::new(AtomicUsize::new(0));
+        let submarine_base_url = fake_submarine(posts.clone());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let tick_loop = task::spawn(tick_loop(
+            config(&submarine_base_url),
+            runtime.clone(),
+            Arc::new(Mutex::new(TickStats::default())),
+            submarine_base_url,
+            reqwest::Client::new(),
+            shutdown_rx,
+        ));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(posts.load(Ordering::SeqCst), 0);
+
+        runtime.lock().await.set_paused(false);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(posts.load(Ordering::SeqCst) > 0);
+
+        shutdown_tx.send(true).unwrap();
+        tick_loop.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn supervise_fails_if_a_subsystem_exits() {
+        let http_server = task::spawn(async { bail!("unable to serve") });
+        let mut tick_loop = task::spawn(future::pending());
+        assert!(supervise(http_server, &mut tick_loop).await.is_err());
+
+        let http_server = task::spawn(future::pending());
+        let mut tick_loop = task::spawn(async { panic!("tick loop panicked") });
+        assert!(supervise(http_server, &mut tick_loop).await.is_err());
+    }
+}
@@ -1,7 +1,11 @@
 use crate::Result;
 use lazy_static::lazy_static;
 use prometheus::exponential_buckets;
-use prometheus::{register_gauge, register_histogram, Gauge, Histogram};
+use prometheus::{
+    register_gauge, register_histogram, register_int_counter, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, Gauge, Histogram, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec,
+};
 use std::net::SocketAddr;
 
 // Runtime-related metrics.
@@ -10,18 +14,61 @@ lazy_static! {
         register_gauge!("loaded_programs", "number of programs loaded").unwrap();
     pub static ref ACTIVE_PROGRAMS: Gauge =
         register_gauge!("active_programs", "number of programs currently active").unwrap();
+    pub static ref FIXTURE_ACTIVE_PROGRAM: IntGaugeVec = register_int_gauge_vec!(
+        "fixture_active_program",
+        "whether a program is the active program of its fixture, 1 if active, 0 otherwise",
+        &["fixture", "program"]
+    )
+    .unwrap();
+    pub static ref MASTER_BRIGHTNESS: Gauge = register_gauge!(
+        "master_brightness",
+        "factor by which all non-binary outputs are scaled, in [0, 1]"
+    )
+    .unwrap();
     pub static ref TICK_DURATION: Histogram = register_histogram!(
         "tick_duration",
         "execution time of currently active programs, in microseconds",
         exponential_buckets(100_f64, (1.5_f64).sqrt(), 10).unwrap()
     )
     .unwrap();
+    pub static ref FIXTURE_TICK_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "fixture_tick_errors",
+        "number of failed ticks per fixture",
+        &["fixture"]
+    )
+    .unwrap();
+    pub static ref FIXTURE_TICKS: IntCounterVec = register_int_counter_vec!(
+        "fixture_ticks",
+        "number of successful ticks per fixture",
+        &["fixture"]
+    )
+    .unwrap();
+    pub static ref TICK_OVERRUNS: IntCounter = register_int_counter!(
+        "tick_overruns",
+        "number of ticks whose tick and send duration exceeded the tick interval"
+    )
+    .unwrap();
     pub static ref SEND_DURATION: Histogram = register_histogram!(
         "send_duration",
         "duration to send set requests of one tick to submarine, in microseconds",
         exponential_buckets(100_f64, (1.5_f64).sqrt(), 10).unwrap()
     )
     .unwrap();
+    pub static ref SEND_COALESCING_RATIO: Gauge = register_gauge!(
+        "send_coalescing_ratio",
+        "number of set requests produced per set request sent to submarine, for the last batch"
+    )
+    .unwrap();
+    pub static ref SUBMARINE_CONNECTED: IntGauge = register_int_gauge!(
+        "submarine_connected",
+        "whether set requests are currently posted to submarine successfully"
+    )
+    .unwrap();
+    pub static ref SUBMARINE_SEND_ERRORS: IntCounter = register_int_counter!(
+        "submarine_send_errors",
+        "number of failed attempts to post set requests to submarine"
+    )
+    .unwrap();
 }
 
 pub(crate) fn start_prometheus(addr: SocketAddr) -> Result<()> {
@@ -0,0 +1,43 @@
+use crate::runtime::fixture::ParameterValue;
+use log::info;
+use serde_json::json;
+
+/// The log target of audit records.
+/// Audit records can be filtered separately, e.g. `RUST_LOG=warn,audit=info`.
+pub(crate) const TARGET: &str = "audit";
+
+/// Records a switch of the active program of a fixture.
+pub(crate) fn program_switched(fixture: &str, old: &str, new: &str) {
+    info!(
+        target: TARGET,
+        "{}",
+        json!({
+            "event": "program_switched",
+            "fixture": fixture,
+            "old": old,
+            "new": new,
+        })
+    );
+}
+
+/// Records a change of a single parameter value.
+pub(crate) fn parameter_changed(
+    fixture: &str,
+    program: &str,
+    parameter: &str,
+    old: &ParameterValue,
+    new: &ParameterValue,
+) {
+    info!(
+        target: TARGET,
+        "{}",
+        json!({
+            "event": "parameter_changed",
+            "fixture": fixture,
+            "program": program,
+            "parameter": parameter,
+            "old": old,
+            "new": new,
+        })
+    );
+}
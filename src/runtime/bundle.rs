@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use log::info;
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Fixtures extracted from a bundle, i.e., a .zip or .tar.gz archive containing fixtures and
+/// programs.
+/// The extracted files are removed when this is dropped.
+pub(crate) struct ExtractedBundle {
+    // Kept to remove the directory on drop.
+    _dir: TempDir,
+    root: PathBuf,
+}
+
+impl ExtractedBundle {
+    /// The directory containing the extracted fixtures.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+/// Extracts the fixtures bundle at the given path, if it is a file with a known archive
+/// extension.
+/// Returns `None` for anything else, e.g., directories.
+pub(crate) fn extract_if_bundle(path: &Path) -> Result<Option<ExtractedBundle>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let dir = tempfile::Builder::new()
+        .prefix("kaleidoscope-fixtures-")
+        .tempdir()
+        .context("unable to create temporary directory")?;
+    let file = File::open(path).context("unable to open bundle")?;
+    if file_name.ends_with(".zip") {
+        zip::ZipArchive::new(file)
+            .context("unable to read zip archive")?
+            .extract(dir.path())
+            .context("unable to extract zip archive")?;
+    } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(dir.path())
+            .context("unable to extract tar archive")?;
+    } else {
+        return Ok(None);
+    }
+    info!("extracted fixtures bundle {:?} to {:?}", path, dir.path());
+
+    // Archives of a fixtures directory usually contain that directory, so we descend into it.
+    let mut entries = fs::read_dir(dir.path())
+        .context("unable to list extracted bundle")?
+        .collect::<std::io::Result<Vec<_>>>()
+        .context("unable to list extracted bundle")?;
+    let root = match entries.pop() {
+        Some(entry) if entries.is_empty() && entry.path().is_dir() => entry.path(),
+        _ => dir.path().to_path_buf(),
+    };
+
+    Ok(Some(ExtractedBundle { _dir: dir, root }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::runtime::Runtime;
+    use crate::runtime::test_util::{self, FixturesDir};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// Loads the fixtures at the given path, activates the program `p` of fixture `f`, and
+    /// returns the outputs of one tick.
+    fn tick_once(path: &Path) -> Vec<(alloy::Address, alloy::OutputValue)> {
+        let mut runtime = Runtime::new(
+            &[path],
+            &test_util::universe_with_aliases(&["a"]),
+            test_util::options(),
+        )
+        .unwrap();
+        runtime
+            .get_fixture_mut("f")
+            .unwrap()
+            .set_active_program("p")
+            .unwrap();
+        runtime
+            .tick()
+            .unwrap()
+            .iter()
+            .map(|r| match r.target {
+                alloy::api::SetRequestTarget::Address(addr) => (addr, r.value),
+                _ => panic!("unexpected target {:?}", r.target),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bundles_load_like_directories() {
+        let fixtures = FixturesDir::new();
+        fixtures.fixture("f", r#"add_output_alias("a") add_program("p", "f/p.lua")"#);
+        fixtures.program("f/p.lua", &test_util::constant_program("a", alloy::HIGH));
+        let expected = tick_once(fixtures.path());
+        assert_eq!(expected, vec![(1, alloy::HIGH)]);
+
+        let out = tempfile::tempdir().unwrap();
+
+        // Contains the fixtures directory.
+        let tar_path = out.path().join("show.tar.gz");
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            File::create(&tar_path).unwrap(),
+            Compression::default(),
+        ));
+        tar.append_dir_all("show", fixtures.path()).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+        assert_eq!(tick_once(&tar_path), expected);
+
+        // Contains the fixtures at the top level.
+        let zip_path = out.path().join("show.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        for name in ["f.lua", "f/p.lua"] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(&fs::read(fixtures.path().join(name)).unwrap())
+                .unwrap();
+        }
+        zip.finish().unwrap();
+        assert_eq!(tick_once(&zip_path), expected);
+    }
+}
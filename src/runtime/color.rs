@@ -0,0 +1,161 @@
+use mlua::Lua;
+
+/// Registers the color conversion functions as Lua globals.
+pub(crate) fn register(lua: &Lua) -> mlua::Result<()> {
+    let globals = lua.globals();
+    globals.set(
+        "hsv_to_rgb",
+        lua.create_function(|_, (h, s, v): (f64, f64, f64)| Ok(hsv_to_rgb(h, s, v)))?,
+    )?;
+    globals.set(
+        "rgb_to_hsv",
+        lua.create_function(|_, (r, g, b): (f64, f64, f64)| Ok(rgb_to_hsv(r, g, b)))?,
+    )?;
+
+    Ok(())
+}
+
+/// Converts a color from HSV to RGB.
+/// The hue wraps around, i.e., 1.25 is the same as 0.25, while saturation and value are
+/// clamped to `[0, 1]`.
+/// All components of the result are in `[0, 1]`.
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (f64, f64, f64) {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let s = s.clamp(0.0, 1.0);
+    let v = v.clamp(0.0, 1.0);
+
+    let sector = h.floor();
+    let f = h - sector;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - s * f);
+    let t = v * (1.0 - s * (1.0 - f));
+
+    match sector as u8 {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        5 => (v, p, q),
+        // rem_euclid can round up to 1.0 for tiny negative hues, which is the same as 0.
+        _ => (v, t, p),
+    }
+}
+
+/// Converts a color from RGB to HSV.
+/// Components are clamped to `[0, 1]`.
+/// The hue of grays, which is undefined, is 0.
+fn rgb_to_hsv(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+    let r = r.clamp(0.0, 1.0);
+    let g = g.clamp(0.0, 1.0);
+    let b = b.clamp(0.0, 1.0);
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0) / 6.0
+    } else if max == g {
+        ((b - r) / delta + 2.0) / 6.0
+    } else {
+        ((r - g) / delta + 4.0) / 6.0
+    };
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+
+    (h, s, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_util::{self, FixturesDir};
+    use alloy::api::SetRequestTarget;
+    use alloy::{HIGH, LOW};
+
+    fn assert_close(actual: (f64, f64, f64), expected: (f64, f64, f64)) {
+        let (a, e) = (
+            [actual.0, actual.1, actual.2],
+            [expected.0, expected.1, expected.2],
+        );
+        assert!(
+            a.iter().zip(e).all(|(a, e)| (a - e).abs() < 1e-9),
+            "{:?}, expected {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn hue_wraps_around() {
+        assert_close(hsv_to_rgb(0.0, 1.0, 1.0), (1.0, 0.0, 0.0));
+        assert_close(hsv_to_rgb(1.0, 1.0, 1.0), (1.0, 0.0, 0.0));
+        assert_close(hsv_to_rgb(1.0 / 3.0, 1.0, 1.0), (0.0, 1.0, 0.0));
+        assert_close(hsv_to_rgb(4.0 / 3.0, 1.0, 1.0), (0.0, 1.0, 0.0));
+        assert_close(hsv_to_rgb(-1.0 / 3.0, 1.0, 1.0), (0.0, 0.0, 1.0));
+        assert_close(hsv_to_rgb(-1e-18, 1.0, 1.0), (1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn zero_saturation_and_value() {
+        // Without saturation, the hue doesn't matter.
+        assert_close(hsv_to_rgb(0.3, 0.0, 0.5), (0.5, 0.5, 0.5));
+        assert_close(hsv_to_rgb(0.7, 1.0, 0.0), (0.0, 0.0, 0.0));
+        assert_close(rgb_to_hsv(0.5, 0.5, 0.5), (0.0, 0.0, 0.5));
+        assert_close(rgb_to_hsv(0.0, 0.0, 0.0), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn round_trip_is_stable() {
+        for i in 0..100 {
+            let h = i as f64 / 100.0;
+            for (s, v) in [(1.0, 1.0), (0.5, 0.8), (0.25, 0.3)] {
+                let (r, g, b) = hsv_to_rgb(h, s, v);
+                assert_close(rgb_to_hsv(r, g, b), (h, s, v));
+            }
+        }
+    }
+
+    #[test]
+    fn programs_can_convert_colors() {
+        let dir = FixturesDir::new();
+        dir.fixture(
+            "f",
+            r#"add_output_alias("r") add_output_alias("g") add_output_alias("b")
+add_program("p", "f/p.lua")"#,
+        );
+        dir.program(
+            "f/p.lua",
+            r#"function setup()
+end
+
+function tick(now)
+    local r, g, b = hsv_to_rgb(4 / 3, 1, 1)
+    return { r = map_to_value(0, 1, r), g = map_to_value(0, 1, g), b = map_to_value(0, 1, b) }
+end
+"#,
+        );
+        let mut runtime = dir.runtime(
+            &test_util::universe_with_aliases(&["r", "g", "b"]),
+            test_util::options(),
+        );
+        runtime
+            .get_fixture_mut("f")
+            .unwrap()
+            .set_active_program("p")
+            .unwrap();
+
+        let values: Vec<_> = runtime
+            .tick()
+            .unwrap()
+            .iter()
+            .map(|r| match r.target {
+                SetRequestTarget::Address(addr) => (addr, r.value),
+                _ => panic!("unexpected target {:?}", r.target),
+            })
+            .collect();
+        assert_eq!(values, vec![(1, LOW), (2, HIGH), (3, LOW)]);
+    }
+}
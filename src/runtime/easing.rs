@@ -0,0 +1,103 @@
+use mlua::Lua;
+use std::f64::consts::PI;
+
+/// An easing function, mapping `[0, 1]` to `[0, 1]`.
+/// Inputs outside of that are clamped.
+type Easing = fn(f64) -> f64;
+
+/// Easing functions of one variable, by their Lua name.
+const EASINGS: &[(&str, Easing)] = &[
+    ("ease_in", ease_in),
+    ("ease_out", ease_out),
+    ("ease_in_out", ease_in_out),
+    ("ease_in_cubic", ease_in_cubic),
+    ("ease_out_cubic", ease_out_cubic),
+    ("ease_in_out_cubic", ease_in_out_cubic),
+    ("ease_in_sine", ease_in_sine),
+    ("ease_out_sine", ease_out_sine),
+    ("ease_in_out_sine", ease_in_out_sine),
+];
+
+/// Registers the easing functions as Lua globals.
+pub(crate) fn register(lua: &Lua) -> mlua::Result<()> {
+    let globals = lua.globals();
+    for (name, f) in EASINGS.iter() {
+        globals.set(*name, lua.create_function(move |_, t: f64| Ok(f(t)))?)?;
+    }
+    globals.set(
+        "smoothstep",
+        lua.create_function(|_, (a, b, x): (f64, f64, f64)| Ok(smoothstep(a, b, x)))?,
+    )?;
+    globals.set(
+        "smootherstep",
+        lua.create_function(|_, (a, b, x): (f64, f64, f64)| Ok(smootherstep(a, b, x)))?,
+    )?;
+
+    Ok(())
+}
+
+fn ease_in(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t
+}
+
+fn ease_out(t: f64) -> f64 {
+    1.0 - ease_in(1.0 - t)
+}
+
+fn ease_in_out(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        2.0 * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+    }
+}
+
+fn ease_in_cubic(t: f64) -> f64 {
+    t.clamp(0.0, 1.0).powi(3)
+}
+
+fn ease_out_cubic(t: f64) -> f64 {
+    1.0 - ease_in_cubic(1.0 - t)
+}
+
+fn ease_in_out_cubic(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+fn ease_in_sine(t: f64) -> f64 {
+    1.0 - (t.clamp(0.0, 1.0) * PI / 2.0).cos()
+}
+
+fn ease_out_sine(t: f64) -> f64 {
+    (t.clamp(0.0, 1.0) * PI / 2.0).sin()
+}
+
+fn ease_in_out_sine(t: f64) -> f64 {
+    -((t.clamp(0.0, 1.0) * PI).cos() - 1.0) / 2.0
+}
+
+/// Maps x from `[a, b]` to `[0, 1]`, clamped.
+/// Degenerate ranges act as a step at `a`.
+fn normalize(a: f64, b: f64, x: f64) -> f64 {
+    if a == b {
+        return if x < a { 0.0 } else { 1.0 };
+    }
+    ((x - a) / (b - a)).clamp(0.0, 1.0)
+}
+
+fn smoothstep(a: f64, b: f64, x: f64) -> f64 {
+    let t = normalize(a, b, x);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn smootherstep(a: f64, b: f64, x: f64) -> f64 {
+    let t = normalize(a, b, x);
+    t * t * t * (t * (6.0 * t - 15.0) + 10.0)
+}
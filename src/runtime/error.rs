@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+/// Well-known failures of the runtime.
+/// Where these occur deep inside the runtime, they are wrapped in an [anyhow::Error] with
+/// additional context, from which they can be recovered using [RuntimeError::find].
+#[derive(Debug, Error)]
+pub(crate) enum RuntimeError {
+    #[error("unknown fixture: {0}")]
+    UnknownFixture(String),
+    #[error("unknown program: {0}")]
+    UnknownProgram(String),
+    #[error("unknown parameter {parameter} for program {program}")]
+    UnknownParameter { program: String, parameter: String },
+    #[error("unknown level: {0}")]
+    UnknownLevel(String),
+    #[error("value {value} is out of range [{lower}, {upper}]")]
+    OutOfRange { value: f64, lower: f64, upper: f64 },
+    #[error("parameter type mismatch: {0}")]
+    TypeMismatch(&'static str),
+    #[error("discrete parameter has no levels")]
+    NoLevels,
+    #[error("program {0} is builtin and can't be reloaded")]
+    BuiltinProgram(String),
+    #[error("source version mismatch: expected {expected}, got {actual}")]
+    VersionMismatch { expected: u16, actual: u16 },
+}
+
+impl RuntimeError {
+    /// Finds a runtime error in the chain of causes of the given error.
+    pub(crate) fn find(err: &anyhow::Error) -> Option<&RuntimeError> {
+        err.chain().find_map(|e| e.downcast_ref::<RuntimeError>())
+    }
+}
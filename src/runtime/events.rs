@@ -0,0 +1,30 @@
+use crate::runtime::fixture::{FixtureState, ParameterValue};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The number of events buffered for each subscriber.
+/// Subscribers which fall further behind miss events.
+pub(crate) const EVENT_CAPACITY: usize = 256;
+
+/// A change to the state of the runtime, published to all subscribers.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum StateEvent {
+    /// The selected programs and parameter values of all fixtures, sent to new subscribers on
+    /// request.
+    Snapshot {
+        fixtures: BTreeMap<String, FixtureState>,
+    },
+    /// The active program of a fixture changed.
+    ProgramSwitched { fixture: String, program: String },
+    /// The value of a parameter changed.
+    ParameterChanged {
+        fixture: String,
+        program: String,
+        parameter: String,
+        value: ParameterValue,
+    },
+    /// The subscriber fell behind and missed events.
+    /// A new snapshot is required to catch up.
+    Lagged { missed: u64 },
+}
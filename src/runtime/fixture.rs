@@ -1,66 +1,292 @@
+use crate::prom;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::events::StateEvent;
 use crate::runtime::runtime::TickState;
+use crate::runtime::sun::SunTimes;
+use crate::runtime::{audit, color, easing};
 use alloy::api::{SetRequest, SetRequestTarget};
 use alloy::config::UniverseConfig;
 use alloy::program::ParameterSetRequest;
 use alloy::{Address, OutputValue, HIGH, LOW};
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use chrono::Timelike;
+use chrono::{DateTime, Datelike, Local, Timelike};
 use lazy_static::lazy_static;
-use log::{debug, trace};
-use mlua::{Function, IntoLua, Lua, Table};
+use log::{debug, info, trace, warn};
+use mlua::{FromLua, Function, IntoLua, Lua, LuaOptions, StdLib, Table};
 use noise::{NoiseFn, Perlin};
-use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
+use std::mem;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 
-/// Number of ticks to skip execution for slow-mode programs.
+/// Number of ticks to skip execution for slow-mode programs, unless specified by the program.
 const SLOW_MODE_NUM_SKIP_TICKS: usize = 999;
 
+/// Derives the seed of the RNG of a program from the names of its fixture and itself, using
+/// 64-bit FNV-1a over `<fixture>/<program>`.
+/// This is stable across runs and versions, unlike the hashers of the standard library.
+fn rng_seed(fixture_name: &str, program_name: &str) -> u64 {
+    format!("{}/{}", fixture_name, program_name)
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// Name of the parameter of the MANUAL program which selects a color preset.
+const MANUAL_COLOR_PRESET_PARAMETER: &str = "preset";
+/// Level of the color preset parameter for individually set channels.
+const MANUAL_COLOR_PRESET_CUSTOM: &str = "custom";
+/// Color presets of the MANUAL program, if a fixture declares color channels but no presets.
+const DEFAULT_MANUAL_COLOR_PRESETS: &[(&str, [f64; 3])] = &[
+    ("red", [1.0, 0.0, 0.0]),
+    ("orange", [1.0, 0.5, 0.0]),
+    ("yellow", [1.0, 1.0, 0.0]),
+    ("green", [0.0, 1.0, 0.0]),
+    ("cyan", [0.0, 1.0, 1.0]),
+    ("blue", [0.0, 0.0, 1.0]),
+    ("magenta", [1.0, 0.0, 1.0]),
+    ("white", [1.0, 1.0, 1.0]),
+];
+
+/// Maximum priority of a fixture.
+const MAX_PRIORITY: u8 = 20;
+
 /// Parameter type constants.
 /// Must be in sync with Lua builtins!
 const PARAMETER_TYPE_DISCRETE: &str = "discrete";
 const PARAMETER_TYPE_CONTINUOUS: &str = "continuous";
+const PARAMETER_TYPE_INTEGER: &str = "integer";
+const PARAMETER_TYPE_COLOR: &str = "color";
 
 /// Runtime version.
-const VERSION: u16 = 3;
+pub(crate) const VERSION: u16 = 3;
 
 lazy_static! {
     pub static ref PERLIN: Perlin = Perlin::new(0);
 }
 
+/// Snapshot of the selected program of every fixture of a runtime, readable by its Lua programs.
+/// This is updated by the runtime before each tick, so that programs don't need to lock the
+/// runtime.
+pub(crate) type SelectedPrograms = Arc<RwLock<HashMap<String, String>>>;
+
+/// Source of parameter revisions, shared by all fixtures so that revisions increase
+/// monotonically across reloads.
+static PARAMETER_REVISION: AtomicU64 = AtomicU64::new(0);
+
+fn next_parameter_revision() -> u64 {
+    PARAMETER_REVISION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Maximum memory a single Lua state may allocate, in bytes.
+const LUA_MEMORY_LIMIT: usize = 32 * 1024 * 1024;
+
 const FIXTURE_BUILTIN_SOURCE: &'static str = include_str!("lua/fixture_builtin.lua");
 const PROGRAM_BUILTIN_SOURCE: &'static str = include_str!("lua/program_builtin.lua");
 
+/// Checks that a fixture name is non-empty and URL-safe, since fixture names are used as path
+/// segments of the HTTP API.
+pub(crate) fn validate_fixture_name(name: &str) -> Result<()> {
+    ensure!(!name.is_empty(), "missing fixture name");
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        bail!(
+            "invalid character {:?} in fixture name {:?}, only ASCII letters, digits, - and _ are allowed",
+            c,
+            name
+        )
+    }
+    Ok(())
+}
+
+/// Maps the output aliases of the universe to addresses, plus the local overrides of aliases.
+/// Both the original and the overridden alias resolve, and overrides shadow aliases of the
+/// universe, see [check_alias_overrides].
+fn resolve_output_aliases(
+    universe: &UniverseConfig,
+    alias_overrides: &HashMap<String, String>,
+) -> HashMap<String, Address> {
+    let mut aliases: HashMap<_, _> = universe
+        .devices
+        .iter()
+        .flat_map(|d| &d.outputs)
+        .map(|output| (output.alias.clone(), output.address))
+        .collect();
+    for (original, overridden) in alias_overrides.iter() {
+        if let Some(addr) = aliases.get(original).copied() {
+            aliases.insert(overridden.clone(), addr);
+        }
+    }
+    aliases
+}
+
+/// Logs overrides of aliases which don't exist in the universe, or which shadow existing aliases.
+pub(crate) fn check_alias_overrides(
+    universe: &UniverseConfig,
+    alias_overrides: &HashMap<String, String>,
+) {
+    let aliases: HashMap<_, _> = universe
+        .devices
+        .iter()
+        .flat_map(|d| &d.outputs)
+        .map(|output| (output.alias.as_str(), output.address))
+        .collect();
+    for (original, overridden) in alias_overrides.iter() {
+        match (
+            aliases.get(original.as_str()),
+            aliases.get(overridden.as_str()),
+        ) {
+            (None, _) => warn!(
+                "ignoring override of alias {}, which is not part of the universe",
+                original
+            ),
+            (Some(addr), Some(shadowed)) if addr != shadowed => warn!(
+                "override {} of alias {} shadows the alias of output {}",
+                overridden, original, shadowed
+            ),
+            _ => {}
+        }
+    }
+}
+
+/// Creates a Lua state for fixtures and programs.
+/// Only the table, string, math, and utf8 standard libraries are loaded. In particular, `os`,
+/// `io`, `debug`, and `package` are unavailable, and the base library functions which load files
+/// are removed. Allocations are limited to [LUA_MEMORY_LIMIT].
+fn new_sandboxed_lua() -> Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8,
+        LuaOptions::default(),
+    )
+    .context("unable to create Lua state")?;
+
+    let globals = lua.globals();
+    globals.set("dofile", mlua::Nil)?;
+    globals.set("loadfile", mlua::Nil)?;
+    drop(globals);
+
+    lua.set_memory_limit(LUA_MEMORY_LIMIT)
+        .context("unable to set memory limit")?;
+
+    Ok(lua)
+}
+
 pub(crate) struct Fixture {
     pub(crate) name: String,
     pub(crate) source_path: PathBuf,
+    /// Paths of the Lua programs of this fixture.
+    pub(crate) program_paths: Vec<PathBuf>,
     pub(crate) addresses: HashSet<Address>,
+    /// Fixtures are ticked in ascending order of priority, i.e., fixtures with higher priority
+    /// are executed later.
+    pub(crate) priority: u8,
+    /// Positions of outputs in space, as declared during setup, for previews.
+    pub(crate) positions: HashMap<Address, Position>,
+    /// Tags for grouping fixtures, e.g., "wash" or "left".
+    pub(crate) tags: BTreeSet<String>,
+    /// Outputs which are only ever on or off, e.g., relays, which are not dimmed by the master
+    /// brightness.
+    pub(crate) binary_outputs: HashSet<Address>,
     programs: Vec<FixtureProgram>,
+    program_slugs: SlugIndex,
     current_program_index: usize,
+    /// Receives changes to the selected program and parameter values, once the fixture is part
+    /// of a runtime.
+    pub(crate) events: Option<broadcast::Sender<StateEvent>>,
 }
 
-impl Fixture {
+/// A fixture which was set up, but whose programs were not loaded yet, see [Fixture::load].
+pub(crate) struct FixtureSetup {
+    source_path: PathBuf,
+    values: FixtureSetupValues,
+}
+
+impl FixtureSetup {
+    /// Runs the setup of the fixture at the given path, without loading its programs.
     pub(crate) fn new<P: AsRef<Path>>(
         source: P,
         universe_config: &UniverseConfig,
-    ) -> Result<Fixture> {
-        let base_path = source
-            .as_ref()
-            .parent()
-            .and_then(|p| Some(p.to_path_buf()))
-            .unwrap_or_else(PathBuf::new);
-
-        // Load and setup fixture
-        let setup_values = Self::setup_fixture(source.as_ref(), universe_config)
-            .context("unable to set up fixture")?;
+        alias_overrides: &HashMap<String, String>,
+    ) -> Result<FixtureSetup> {
+        let setup_values =
+            Fixture::setup_fixture(source.as_ref(), universe_config, alias_overrides)
+                .context("unable to set up fixture")?;
         debug!(
             "set up fixture at {:?}: {:?}",
             source.as_ref(),
             setup_values
         );
 
+        Ok(FixtureSetup {
+            source_path: source.as_ref().to_path_buf(),
+            values: setup_values,
+        })
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.values.name
+    }
+
+    pub(crate) fn source_path(&self) -> &Path {
+        &self.source_path
+    }
+
+    /// The name of the program the fixture starts with, i.e., its first program.
+    pub(crate) fn initial_program(&self) -> &'static str {
+        if self.values.disable_builtin_programs {
+            "EXTERNAL"
+        } else {
+            "OFF"
+        }
+    }
+
+    /// Paths of the sources of the Lua programs of the fixture.
+    pub(crate) fn program_paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        let base_path = self.source_path.parent().unwrap_or_else(|| Path::new(""));
+        self.values
+            .program_sources
+            .iter()
+            .map(move |(_, source)| base_path.join(source))
+    }
+}
+
+impl Fixture {
+    pub(crate) fn new<P: AsRef<Path>>(
+        source: P,
+        universe_config: &UniverseConfig,
+        alias_overrides: &HashMap<String, String>,
+        selected_programs: &SelectedPrograms,
+    ) -> Result<Fixture> {
+        let setup = FixtureSetup::new(source, universe_config, alias_overrides)?;
+        Self::load(setup, universe_config, alias_overrides, selected_programs)
+    }
+
+    /// Loads the programs of a fixture which was set up.
+    /// Metrics are not exported until the fixture is part of a runtime, see
+    /// [Fixture::export_metrics].
+    /// Programs can read the selected programs of other fixtures from the given snapshot.
+    pub(crate) fn load(
+        setup: FixtureSetup,
+        universe_config: &UniverseConfig,
+        alias_overrides: &HashMap<String, String>,
+        selected_programs: &SelectedPrograms,
+    ) -> Result<Fixture> {
+        let program_paths: Vec<_> = setup.program_paths().collect();
+        let FixtureSetup {
+            source_path,
+            values: setup_values,
+        } = setup;
+
         let output_aliases: HashMap<_, _> = universe_config
             .devices
             .iter()
@@ -68,58 +294,78 @@ impl Fixture {
             .filter(|o| setup_values.outputs.contains(&o.address))
             .map(|ref o| (o.alias.clone(), o.address))
             .collect();
+        // Programs can refer to outputs by their overridden aliases as well, but the MANUAL
+        // program keeps the aliases of the universe.
+        let program_output_aliases: HashMap<_, _> =
+            resolve_output_aliases(universe_config, alias_overrides)
+                .into_iter()
+                .filter(|(_, addr)| setup_values.outputs.contains(addr))
+                .collect();
 
         // Load and setup programs
         let mut lua_programs = Vec::new();
-        for (program_name, program_source) in setup_values.program_sources.iter() {
-            let program_source_path = base_path.clone().join(program_source);
-
-            let program =
-                LuaFixtureProgram::new(&program_source_path, output_aliases.clone(), 0).context(
-                    format!("unable to load program at {:?}", program_source_path),
-                )?;
+        for ((program_name, _), program_source_path) in setup_values
+            .program_sources
+            .iter()
+            .zip(program_paths.iter())
+        {
+            let program = LuaFixtureProgram::new(
+                program_source_path,
+                program_output_aliases.clone(),
+                Local::now(),
+                rng_seed(&setup_values.name, program_name),
+                selected_programs.clone(),
+            )
+            .context(format!(
+                "unable to load program at {:?}",
+                program_source_path
+            ))?;
 
             lua_programs.push((program_name.clone(), program))
         }
 
         let mut programs = Vec::new();
 
-        // Add builtin programs
+        // Add builtin programs.
+        // The first program is the initial program, see [FixtureSetup::initial_program].
         if !setup_values.disable_builtin_programs {
-            programs.push(FixtureProgram {
-                name: "OFF".to_string(),
-                inner: FixtureProgramType::BundledConstant(
+            programs.push(FixtureProgram::new(
+                "OFF".to_string(),
+                FixtureProgramType::BundledConstant(
                     BundledConstantFixtureProgram::new_fixed_value(
                         setup_values.outputs.clone(),
                         LOW,
                     ),
                 ),
-            });
-            programs.push(FixtureProgram {
-                name: "ON".to_string(),
-                inner: FixtureProgramType::BundledConstant(
+            ));
+            programs.push(FixtureProgram::new(
+                "ON".to_string(),
+                FixtureProgramType::BundledConstant(
                     BundledConstantFixtureProgram::new_fixed_value(
                         setup_values.outputs.clone(),
                         HIGH,
                     ),
                 ),
-            });
+            ));
         }
 
         // Add EXTERNAL program, indicating the fixture is not controlled by Kaleidoscope.
-        programs.push(FixtureProgram {
-            name: "EXTERNAL".to_string(),
-            inner: FixtureProgramType::External,
-        });
+        programs.push(FixtureProgram::new(
+            "EXTERNAL".to_string(),
+            FixtureProgramType::External,
+        ));
 
         // Add MANUAL program, with parameters for all aliases
         if !setup_values.disable_manual_program {
-            programs.push(FixtureProgram {
-                name: "MANUAL".to_string(),
-                inner: FixtureProgramType::BundledManual(BundledManualFixtureProgram::new(
+            programs.push(FixtureProgram::new(
+                "MANUAL".to_string(),
+                FixtureProgramType::BundledManual(BundledManualFixtureProgram::new(
                     output_aliases.clone(),
+                    program_output_aliases.clone(),
+                    &setup_values.manual_ranges,
+                    setup_values.manual_color_presets.clone(),
                 )),
-            });
+            ));
         }
 
         // Add Lua programs
@@ -127,10 +373,7 @@ impl Fixture {
             if programs.iter().find(|p| p.name == name).is_some() {
                 bail!("duplicate/conflicting program name: {}", name)
             }
-            programs.push(FixtureProgram {
-                name,
-                inner: FixtureProgramType::Lua(program),
-            })
+            programs.push(FixtureProgram::new(name, FixtureProgramType::Lua(program)))
         }
 
         ensure!(
@@ -138,32 +381,98 @@ impl Fixture {
             "no programs defined and builtin programs disabled"
         );
 
+        let program_slugs = SlugIndex::new(programs.iter().map(|p| p.name.as_str()));
+        if program_slugs.has_ambiguous() {
+            warn!(
+                "{}: some program names map to the same slug and can only be addressed by their exact name",
+                setup_values.name
+            );
+        }
+
         Ok(Fixture {
             name: setup_values.name,
             addresses: setup_values.outputs,
-            source_path: source.as_ref().to_path_buf(),
+            priority: setup_values.priority,
+            positions: setup_values.positions,
+            tags: setup_values.tags,
+            binary_outputs: setup_values.binary_outputs,
+            source_path,
+            program_paths,
             programs,
+            program_slugs,
             current_program_index: 0,
+            events: None,
         })
     }
 
+    pub(crate) fn num_programs(&self) -> usize {
+        self.programs.len()
+    }
+
+    fn set_program_active_metric(&self, program_name: &str, active: bool) {
+        prom::FIXTURE_ACTIVE_PROGRAM
+            .with_label_values(&[&self.name, program_name])
+            .set(active as i64);
+    }
+
+    /// Exports which of the programs of this fixture is active.
+    pub(crate) fn export_metrics(&self) {
+        for (i, program) in self.programs.iter().enumerate() {
+            self.set_program_active_metric(&program.name, i == self.current_program_index);
+        }
+    }
+
+    /// Stops exporting metrics about the programs of this fixture, e.g., because it was removed.
+    pub(crate) fn remove_metrics(&self) {
+        for program in self.programs.iter() {
+            let _ = prom::FIXTURE_ACTIVE_PROGRAM.remove_label_values(&[&self.name, &program.name]);
+        }
+    }
+
+    /// Looks up a program by its name or slug.
     pub(crate) fn get_program(&self, name: &str) -> Option<&FixtureProgram> {
-        self.programs.iter().find(|p| p.name == name)
+        self.program_index(name).ok().map(|i| &self.programs[i])
     }
 
+    /// Looks up a program by its name or slug.
     pub(crate) fn get_program_mut(&mut self, name: &str) -> Option<&mut FixtureProgram> {
-        self.programs.iter_mut().find(|p| p.name == name)
+        self.program_index(name).ok().map(|i| &mut self.programs[i])
     }
 
-    pub(crate) fn alloy_metadata(
-        &self,
-        universe_config: &UniverseConfig,
-    ) -> alloy::program::FixtureMetadata {
-        alloy::program::FixtureMetadata {
+    /// Resolves the name or slug of a program to its index.
+    fn program_index(&self, name: &str) -> std::result::Result<usize, RuntimeError> {
+        self.program_slugs
+            .resolve(self.programs.iter().map(|p| p.name.as_str()), name)
+            .ok_or_else(|| RuntimeError::UnknownProgram(name.to_string()))
+    }
+
+    /// Returns the parameters of all programs which changed after the given revision.
+    pub(crate) fn parameter_changes_since(&self, since: u64) -> ParameterChanges {
+        let mut changes = ParameterChanges {
+            revision: 0,
+            programs: HashMap::new(),
+        };
+        for program in self.programs.iter() {
+            for parameter in parameters_of(&program.inner) {
+                changes.revision = changes.revision.max(parameter.revision);
+                if parameter.revision > since {
+                    changes
+                        .programs
+                        .entry(program.name.clone())
+                        .or_default()
+                        .insert(parameter.name.clone(), parameter.metadata());
+                }
+            }
+        }
+        changes
+    }
+
+    pub(crate) fn metadata(&self, universe_config: &UniverseConfig) -> FixtureMetadata {
+        FixtureMetadata {
             programs: self
                 .programs
                 .iter()
-                .map(|p| (p.name.clone(), p.alloy_metadata()))
+                .map(|p| (p.name.clone(), p.metadata()))
                 .collect(),
             selected_program: self
                 .programs
@@ -178,19 +487,24 @@ impl Fixture {
                 .filter(|o| self.addresses.contains(&o.address))
                 .map(|ref o| o.alias.clone())
                 .collect(),
+            tags: self.tags.clone(),
         }
     }
 
+    /// Returns whether this fixture carries the given tag.
+    pub(crate) fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
     fn setup_fixture<P: AsRef<Path>>(
         source: P,
         universe_config: &UniverseConfig,
+        alias_overrides: &HashMap<String, String>,
     ) -> Result<FixtureSetupValues> {
-        let lua = Lua::new();
+        let lua = new_sandboxed_lua()?;
         debug!("loading fixture at {:?}...", source.as_ref());
         let fixture_source = fs::read_to_string(source).context("unable to read fixture source")?;
 
-        lua.load_from_std_lib(mlua::StdLib::TABLE)?;
-
         let globals = lua.globals();
 
         lua.load(FIXTURE_BUILTIN_SOURCE)
@@ -204,34 +518,45 @@ impl Fixture {
 
         // check source version
         let source_version: u16 = globals.get("SOURCE_VERSION")?;
-        ensure!(source_version == VERSION, "source version mismatch");
+        ensure!(
+            source_version == VERSION,
+            RuntimeError::VersionMismatch {
+                expected: VERSION,
+                actual: source_version,
+            }
+        );
 
-        let setup_values =
-            Self::setup(&lua, universe_config).context("failed to execute fixture::setup")?;
+        let setup_values = Self::setup(&lua, universe_config, alias_overrides)
+            .context("failed to execute fixture::setup")?;
 
         Ok(setup_values)
     }
 
-    pub(crate) fn set_active_program(&mut self, to: &str) -> Result<()> {
-        let pos = self
-            .programs
-            .iter()
-            .position(|p| &p.name == to)
-            .ok_or(anyhow!("not found"))?;
+    pub(crate) fn set_active_program(&mut self, to: &str) -> std::result::Result<(), RuntimeError> {
+        let pos = self.program_index(to)?;
         self.switch_program(pos)
             .expect("invalid index in set_active_program");
         Ok(())
     }
 
-    pub(crate) fn cycle_active_program(&mut self) -> Result<String> {
+    /// Returns the name of the currently selected program.
+    pub(crate) fn active_program(&self) -> &str {
+        &self.programs[self.current_program_index].name
+    }
+
+    /// Cycles to the next program.
+    /// Unless `include_all` is set, MANUAL and EXTERNAL are skipped.
+    pub(crate) fn cycle_active_program(&mut self, include_all: bool) -> Result<String> {
         if self.programs.is_empty() {
             bail!("no programs available")
         }
         let mut next_index = (self.current_program_index + 1) % self.programs.len();
-        while match self.programs[next_index].name.as_str() {
-            "MANUAL" | "EXTERNAL" => true,
-            _ => false,
-        } {
+        while !include_all
+            && match self.programs[next_index].name.as_str() {
+                "MANUAL" | "EXTERNAL" => true,
+                _ => false,
+            }
+        {
             // Skip those two
             next_index = (next_index + 1) % self.programs.len();
         }
@@ -243,11 +568,28 @@ impl Fixture {
     fn switch_program(&mut self, to: usize) -> Result<()> {
         ensure!(to <= self.programs.len(), "invalid index");
 
+        if to != self.current_program_index {
+            audit::program_switched(
+                &self.name,
+                &self.programs[self.current_program_index].name,
+                &self.programs[to].name,
+            );
+            self.publish(StateEvent::ProgramSwitched {
+                fixture: self.name.clone(),
+                program: self.programs[to].name.clone(),
+            });
+        }
+        self.programs
+            .get_mut(self.current_program_index)
+            .unwrap()
+            .disable();
+        self.set_program_active_metric(&self.programs[self.current_program_index].name, false);
         self.current_program_index = to;
         self.programs
             .get_mut(self.current_program_index)
             .unwrap()
             .enable();
+        self.set_program_active_metric(&self.programs[self.current_program_index].name, true);
 
         Ok(())
     }
@@ -263,17 +605,288 @@ impl Fixture {
             .run(state, output_requests)
     }
 
-    fn setup(lua: &Lua, universe: &UniverseConfig) -> Result<FixtureSetupValues> {
+    /// Loads a Lua program of this fixture again from its source, keeping parameter values where
+    /// possible, see [LuaFixtureProgram::reload].
+    /// If the program fails to load, the previous version is kept.
+    pub(crate) fn reload_program(&mut self, program_name: &str) -> Result<()> {
+        let index = self.program_index(program_name)?;
+        let program = &self.programs[index];
+        info!("{}: reloading program {}...", self.name, program.name);
+        let program = program.reload(Local::now()).context(format!(
+            "unable to reload program {} of fixture {}",
+            program.name, self.name
+        ))?;
+
+        if index == self.current_program_index {
+            // Let the outgoing instance clean up, like on a program switch.
+            self.programs[index].disable();
+            self.programs[index] = program;
+            self.programs[index].enable();
+        } else {
+            self.programs[index] = program;
+        }
+
+        Ok(())
+    }
+
+    /// Takes over parameter values from a previous version of this fixture, by program and
+    /// parameter name, e.g., after a reload.
+    pub(crate) fn migrate_parameters_from(&mut self, previous: &Fixture) {
+        for program in self.programs.iter_mut() {
+            if let Some(previous) = previous.programs.iter().find(|p| p.name == program.name) {
+                program.migrate_parameters_from(previous);
+            }
+        }
+    }
+
+    /// Removes set requests for addresses which are not outputs of this fixture, e.g., written
+    /// by a buggy program, and returns these addresses.
+    pub(crate) fn retain_declared_outputs(
+        &self,
+        output_requests: &mut Vec<SetRequest>,
+    ) -> BTreeSet<Address> {
+        let mut undeclared = BTreeSet::new();
+        output_requests.retain(|req| match req.target {
+            SetRequestTarget::Address(addr) if !self.addresses.contains(&addr) => {
+                undeclared.insert(addr);
+                false
+            }
+            _ => true,
+        });
+        undeclared
+    }
+
+    /// Computes the outputs the named program would produce `at` after being enabled, with the
+    /// given parameter values overriding the current ones.
+    /// This runs an independent instance of the program, i.e., neither the program nor its
+    /// parameters are modified.
+    pub(crate) fn preview(
+        &self,
+        program_name: &str,
+        parameters: BTreeMap<String, ParameterRequest>,
+        at: Duration,
+        local_time: DateTime<Local>,
+        sun_times: Option<SunTimes>,
+    ) -> Result<Vec<SetRequest>> {
+        let program = self
+            .get_program(program_name)
+            .ok_or_else(|| RuntimeError::UnknownProgram(program_name.to_string()))?;
+        let mut preview = program.preview_instance(local_time)?;
+        if let Some((name, Err(err))) = preview
+            .set_parameters(parameters)
+            .into_iter()
+            .find(|(_, res)| res.is_err())
+        {
+            return Err(anyhow::Error::new(err).context(format!("invalid parameter {}", name)));
+        }
+        preview.enable();
+
+        let epoch = match &preview.inner {
+            FixtureProgramType::Lua(p) => p.epoch,
+            _ => Instant::now(),
+        };
+        let state = TickState {
+            timestamp: epoch + at,
+            local_time,
+            sun_times,
+        };
+        let mut output_requests = Vec::new();
+        preview.run(&state, &mut output_requests)?;
+        self.retain_declared_outputs(&mut output_requests);
+        Ok(output_requests)
+    }
+
+    /// Exports the currently selected program and all parameter values of this fixture.
+    pub(crate) fn export_state(&self) -> FixtureState {
+        FixtureState {
+            selected_program: self.active_program().to_string(),
+            programs: self
+                .programs
+                .iter()
+                .map(|p| (p.name.clone(), p.export_parameters()))
+                .filter(|(_, params)| !params.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Imports a previously exported state.
+    /// All values are validated before any of them are applied, i.e., either the entire state
+    /// is imported, or nothing is changed.
+    pub(crate) fn import_state(&mut self, state: FixtureState) -> Result<()> {
+        let selected_program = self.program_index(&state.selected_program)?;
+
+        let mut new_values = Vec::new();
+        for (program_name, parameters) in state.programs.into_iter() {
+            let program_index = self.program_index(&program_name)?;
+            let program = &self.programs[program_index];
+
+            for (parameter_name, value) in parameters.into_iter() {
+                let parameter = program.with_parameter_value(&parameter_name, value.into())?;
+                new_values.push((program_index, parameter));
+            }
+        }
+
+        // Everything checks out, apply.
+        self.apply_parameters(new_values);
+        self.switch_program(selected_program)
+            .expect("invalid index in import_state");
+
+        Ok(())
+    }
+
+    /// Restores a previously saved state, as far as it still applies.
+    /// Unlike [Fixture::import_state], programs, parameters, and values which no longer exist or
+    /// are no longer valid are skipped.
+    pub(crate) fn restore_state(&mut self, state: FixtureState) {
+        let mut new_values = Vec::new();
+        for (program_name, parameters) in state.programs.into_iter() {
+            let program_index = match self.programs.iter().position(|p| p.name == program_name) {
+                Some(i) => i,
+                None => {
+                    debug!(
+                        "{}: skipping saved state of unknown program {}",
+                        self.name, program_name
+                    );
+                    continue;
+                }
+            };
+            let program = &self.programs[program_index];
+
+            for (parameter_name, value) in parameters.into_iter() {
+                match program.with_parameter_value(&parameter_name, value.into()) {
+                    Ok(parameter) => new_values.push((program_index, parameter)),
+                    Err(err) => debug!(
+                        "{}: skipping saved value of parameter {} of program {}: {:#}",
+                        self.name, parameter_name, program_name, err
+                    ),
+                }
+            }
+        }
+
+        self.apply_parameters(new_values);
+        if let Err(err) = self.set_active_program(&state.selected_program) {
+            debug!("{}: not restoring selected program: {}", self.name, err)
+        }
+    }
+
+    /// Sets parameters of a program from an object shaped like the program's metadata.
+    /// Only the current values are used, everything else is ignored.
+    /// All values are validated before any of them are applied.
+    pub(crate) fn set_program_parameters(
+        &mut self,
+        program_name: &str,
+        metadata: ProgramParametersRequest,
+    ) -> Result<()> {
+        let program_index = self.program_index(program_name)?;
+        let program = &self.programs[program_index];
+
+        let mut new_values = Vec::new();
+        for (parameter_name, parameter) in metadata.parameters.into_iter() {
+            let value = match parameter {
+                ParameterMetadataRequest::Alloy(parameter) => match parameter.inner {
+                    alloy::program::ParameterType::Discrete { current_level, .. } => {
+                        ParameterSetRequest::Discrete {
+                            level: current_level,
+                        }
+                    }
+                    alloy::program::ParameterType::Continuous { current, .. } => {
+                        ParameterSetRequest::Continuous { value: current }
+                    }
+                }
+                .into(),
+                ParameterMetadataRequest::Color {
+                    inner: ColorParameterMetadata::Color { current },
+                } => ParameterRequest::Color(ColorSetRequest::Color { value: current }),
+            };
+            let parameter = program.with_parameter_value(&parameter_name, value)?;
+            new_values.push((program_index, parameter));
+        }
+
+        self.apply_parameters(new_values);
+
+        Ok(())
+    }
+
+    /// Replaces parameters with previously validated copies, by program index.
+    fn apply_parameters(&mut self, parameters: Vec<(usize, FixtureProgramParameter)>) {
+        for (program_index, parameter) in parameters.into_iter() {
+            let name = parameter.name.clone();
+            let program = &mut self.programs[program_index];
+            let program_name = program.name.clone();
+            let current = program.get_parameter_mut(&name).unwrap();
+            let old = current.value.current_value();
+            *current = parameter;
+            let new = current.value.current_value();
+            if old != new {
+                self.record_parameter_change(&program_name, &name, &old, new);
+            }
+        }
+    }
+
+    /// Records every parameter of a program whose value differs between two snapshots, as taken by
+    /// [FixtureProgram::parameter_values], to the audit log and subscribers.
+    pub(crate) fn record_parameter_changes(
+        &self,
+        program: &str,
+        old: &BTreeMap<String, ParameterValue>,
+        new: BTreeMap<String, ParameterValue>,
+    ) {
+        for (parameter, new_value) in new.into_iter() {
+            if let Some(old_value) = old.get(&parameter) {
+                if *old_value != new_value {
+                    self.record_parameter_change(program, &parameter, old_value, new_value);
+                }
+            }
+        }
+    }
+
+    fn record_parameter_change(
+        &self,
+        program: &str,
+        parameter: &str,
+        old: &ParameterValue,
+        new: ParameterValue,
+    ) {
+        audit::parameter_changed(&self.name, program, parameter, old, &new);
+        self.publish(StateEvent::ParameterChanged {
+            fixture: self.name.clone(),
+            program: program.to_string(),
+            parameter: parameter.to_string(),
+            value: new,
+        });
+    }
+
+    fn publish(&self, event: StateEvent) {
+        if let Some(events) = &self.events {
+            // Sending only fails if nobody is subscribed.
+            let _ = events.send(event);
+        }
+    }
+
+    fn setup(
+        lua: &Lua,
+        universe: &UniverseConfig,
+        alias_overrides: &HashMap<String, String>,
+    ) -> Result<FixtureSetupValues> {
         let mut disable_builtin = false;
         let mut disable_manual = false;
+        let mut manual_color_channels: Option<[String; 3]> = None;
+        let mut manual_color_presets: Vec<(String, [f64; 3])> = Vec::new();
+        let mut priority = 0;
         let mut name = String::new();
         let mut outputs: HashSet<Address> = HashSet::new();
+        let mut positions: HashMap<Address, Position> = HashMap::new();
+        let mut manual_ranges: HashMap<Address, (f64, f64)> = HashMap::new();
+        let mut binary_outputs: HashSet<Address> = HashSet::new();
+        let mut tags: BTreeSet<String> = BTreeSet::new();
         let mut program_sources: Vec<(String, String)> = Vec::new();
-        let output_aliases: HashMap<_, _> = universe
+        let output_aliases = resolve_output_aliases(universe, alias_overrides);
+        // Overrides can shadow the only alias of an output, which keeps its address in use.
+        let addresses: HashSet<Address> = universe
             .devices
             .iter()
             .flat_map(|d| &d.outputs)
-            .map(|output| (output.alias.clone(), output.address))
+            .map(|output| output.address)
             .collect();
 
         let globals = lua.globals();
@@ -301,6 +914,27 @@ impl Fixture {
             })?;
             globals.set("disable_manual_program", disable_manual_program)?;
 
+            let set_priority = scope.create_function_mut(|_, p_priority: u8| {
+                if p_priority > MAX_PRIORITY {
+                    return Err(mlua::Error::external(format!(
+                        "priority must be at most {}, got {}",
+                        MAX_PRIORITY, p_priority
+                    )));
+                }
+                priority = p_priority;
+                Ok(())
+            })?;
+            globals.set("set_priority", set_priority)?;
+
+            let fixture_tags = scope.create_function_mut(|_, p_tags: Vec<String>| {
+                if p_tags.iter().any(|t| t.is_empty()) {
+                    return Err(mlua::Error::external("tags must not be empty"));
+                }
+                tags.extend(p_tags);
+                Ok(())
+            })?;
+            globals.set("fixture_tags", fixture_tags)?;
+
             let add_program_source =
                 scope.create_function_mut(|_, (program_name, source_path): (String, String)| {
                     if let Some(_) = program_sources
@@ -320,7 +954,7 @@ impl Fixture {
             globals.set("add_program", add_program_source)?;
 
             let add_output_address = scope.create_function_mut(|_, address: Address| {
-                if let None = output_aliases.iter().find(|(_a, b)| **b == address) {
+                if !addresses.contains(&address) {
                     return Err(mlua::Error::external(format!(
                         "address not in use: {}",
                         address
@@ -345,18 +979,173 @@ impl Fixture {
             })?;
             globals.set("add_output_alias", add_output_alias)?;
 
+            let set_position = scope.create_function_mut(
+                |_, (alias, x, y, z): (String, f64, f64, Option<f64>)| {
+                    let addr = *output_aliases.get(&alias).ok_or_else(|| {
+                        mlua::Error::external(format!("unknown alias: {}", alias))
+                    })?;
+
+                    positions.insert(
+                        addr,
+                        Position {
+                            x,
+                            y,
+                            z: z.unwrap_or(0.0),
+                        },
+                    );
+
+                    Ok(())
+                },
+            )?;
+            globals.set("set_position", set_position)?;
+
+            let set_output_binary = scope.create_function_mut(|_, alias: String| {
+                let addr = *output_aliases
+                    .get(&alias)
+                    .ok_or_else(|| mlua::Error::external(format!("unknown alias: {}", alias)))?;
+                binary_outputs.insert(addr);
+                Ok(())
+            })?;
+            globals.set("set_output_binary", set_output_binary)?;
+
+            let set_manual_range = scope.create_function_mut(
+                |_, (alias, lower_limit_incl, upper_limit_incl): (String, f64, f64)| {
+                    let addr = *output_aliases.get(&alias).ok_or_else(|| {
+                        mlua::Error::external(format!("unknown alias: {}", alias))
+                    })?;
+                    if !lower_limit_incl.is_finite()
+                        || !upper_limit_incl.is_finite()
+                        || lower_limit_incl >= upper_limit_incl
+                    {
+                        return Err(mlua::Error::external(format!(
+                            "invalid range [{}, {}] for alias {}",
+                            lower_limit_incl, upper_limit_incl, alias
+                        )));
+                    }
+
+                    manual_ranges.insert(addr, (lower_limit_incl, upper_limit_incl));
+
+                    Ok(())
+                },
+            )?;
+            globals.set("set_manual_range", set_manual_range)?;
+
+            let set_manual_color_channels =
+                scope.create_function_mut(|_, (r, g, b): (String, String, String)| {
+                    if let Some(alias) = [&r, &g, &b]
+                        .into_iter()
+                        .find(|alias| !output_aliases.contains_key(*alias))
+                    {
+                        return Err(mlua::Error::external(format!("unknown alias: {}", alias)));
+                    }
+                    // The MANUAL program names its parameters after the aliases of the universe.
+                    let original = |alias: String| {
+                        alias_overrides
+                            .iter()
+                            .find(|(_, overridden)| **overridden == alias)
+                            .map_or(alias, |(original, _)| original.clone())
+                    };
+                    manual_color_channels = Some([original(r), original(g), original(b)]);
+                    Ok(())
+                })?;
+            globals.set("set_manual_color_channels", set_manual_color_channels)?;
+
+            let add_manual_color_preset = scope.create_function_mut(
+                |_, (preset_name, r, g, b): (String, f64, f64, f64)| {
+                    if preset_name == MANUAL_COLOR_PRESET_CUSTOM
+                        || manual_color_presets.iter().any(|(n, _)| *n == preset_name)
+                    {
+                        return Err(mlua::Error::external(format!(
+                            "duplicate or reserved color preset name: {}",
+                            preset_name
+                        )));
+                    }
+                    if let Some(c) = [r, g, b].iter().find(|c| !(0.0..=1.0).contains(*c)) {
+                        return Err(mlua::Error::external(format!(
+                            "component {} of color preset {} is out of range [0, 1]",
+                            c, preset_name
+                        )));
+                    }
+                    manual_color_presets.push((preset_name, [r, g, b]));
+                    Ok(())
+                },
+            )?;
+            globals.set("add_manual_color_preset", add_manual_color_preset)?;
+
             // Actually call setup
             setup.call(())?;
 
             Ok(())
         })?;
 
+        validate_fixture_name(&name)?;
+
+        if let Some(addr) = positions.keys().find(|addr| !outputs.contains(addr)) {
+            bail!(
+                "position set for output {} which is not part of the fixture",
+                addr
+            )
+        }
+        if let Some(addr) = binary_outputs.iter().find(|addr| !outputs.contains(addr)) {
+            bail!("output {} declared binary is not part of the fixture", addr)
+        }
+        if let Some(addr) = manual_ranges.keys().find(|addr| !outputs.contains(addr)) {
+            bail!(
+                "manual range set for output {} which is not part of the fixture",
+                addr
+            )
+        }
+
+        let manual_color_presets = match manual_color_channels {
+            Some(channels) => {
+                if let Some(alias) = channels
+                    .iter()
+                    .find(|alias| !outputs.contains(&output_aliases[*alias]))
+                {
+                    bail!(
+                        "color channel {} of the MANUAL program is not part of the fixture",
+                        alias
+                    )
+                }
+                ensure!(
+                    !output_aliases
+                        .get(MANUAL_COLOR_PRESET_PARAMETER)
+                        .map_or(false, |addr| outputs.contains(addr)),
+                    "output alias {} clashes with the color preset parameter of the MANUAL program",
+                    MANUAL_COLOR_PRESET_PARAMETER
+                );
+                if manual_color_presets.is_empty() {
+                    manual_color_presets = DEFAULT_MANUAL_COLOR_PRESETS
+                        .iter()
+                        .map(|(name, color)| (name.to_string(), *color))
+                        .collect();
+                }
+                Some(ManualColorPresets {
+                    channels,
+                    presets: manual_color_presets,
+                })
+            }
+            None => {
+                ensure!(
+                    manual_color_presets.is_empty(),
+                    "color presets declared without color channels"
+                );
+                None
+            }
+        };
+
         Ok(FixtureSetupValues {
             name,
             program_sources,
             outputs,
+            positions,
+            tags,
+            binary_outputs,
             disable_builtin_programs: disable_builtin,
             disable_manual_program: disable_manual,
+            manual_ranges,
+            manual_color_presets,
+            priority,
         })
     }
 }
@@ -366,30 +1155,297 @@ struct FixtureSetupValues {
     name: String,
     program_sources: Vec<(String, String)>,
     outputs: HashSet<Address>,
+    positions: HashMap<Address, Position>,
+    tags: BTreeSet<String>,
+    binary_outputs: HashSet<Address>,
     disable_builtin_programs: bool,
     disable_manual_program: bool,
+    /// Ranges of the continuous parameters of the MANUAL program, if not `[0, 1]`.
+    manual_ranges: HashMap<Address, (f64, f64)>,
+    manual_color_presets: Option<ManualColorPresets>,
+    priority: u8,
 }
 
-pub(crate) struct FixtureProgram {
-    name: String,
-    inner: FixtureProgramType,
+/// Named colors for the MANUAL program, which set three of its channels at once.
+#[derive(Clone, Debug)]
+struct ManualColorPresets {
+    /// Aliases of the red, green, and blue channels.
+    channels: [String; 3],
+    presets: Vec<(String, [f64; 3])>,
 }
 
-impl FixtureProgram {
-    fn enable(&mut self) {
-        match &mut self.inner {
-            FixtureProgramType::BundledConstant(p) => p.enable(),
-            FixtureProgramType::Lua(p) => p.enable(),
-            FixtureProgramType::BundledManual(p) => p.enable(),
-            FixtureProgramType::External => {}
-        }
-    }
+/// The position of an output in space, in arbitrary units.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub(crate) struct Position {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) z: f64,
+}
 
-    fn run(&mut self, state: &TickState, output_requests: &mut Vec<SetRequest>) -> Result<()> {
-        match &mut self.inner {
-            FixtureProgramType::BundledConstant(p) => p.run(state, output_requests),
-            FixtureProgramType::Lua(p) => p.run(state, output_requests),
-            FixtureProgramType::BundledManual(p) => p.run(state, output_requests),
+/// Metadata about a fixture.
+/// This has the same shape as [alloy::program::FixtureMetadata], but is extended with
+/// additional information about programs and parameters.
+#[derive(Serialize)]
+pub(crate) struct FixtureMetadata {
+    programs: HashMap<String, ProgramMetadata>,
+    selected_program: String,
+    output_aliases: BTreeSet<String>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    tags: BTreeSet<String>,
+}
+
+/// Parameters which changed after a given revision, see [Fixture::parameter_changes_since].
+#[derive(Serialize)]
+pub(crate) struct ParameterChanges {
+    /// The highest revision of any parameter of the fixture.
+    revision: u64,
+    /// Changed parameters by program name and parameter name.
+    programs: HashMap<String, HashMap<String, ParameterMetadata>>,
+}
+
+/// Metadata about a program, see [alloy::program::ProgramMetadata].
+#[derive(Serialize)]
+pub(crate) struct ProgramMetadata {
+    parameters: HashMap<String, ParameterMetadata>,
+}
+
+/// Metadata about a parameter.
+/// This extends [alloy::program::ProgramParameter] with optional display hints.
+#[derive(Serialize)]
+pub(crate) struct ParameterMetadata {
+    #[serde(flatten)]
+    inner: ParameterMetadataInner,
+    unit: Option<String>,
+    /// The granularity of continuous values, if any.
+    step: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display: Option<String>,
+    /// The value declared during setup, e.g., to indicate modified parameters.
+    default: ParameterValue,
+    /// The revision of the last change, see [Fixture::parameter_changes_since].
+    revision: u64,
+    /// Whether the parameter only takes integer values.
+    /// Alloy has no integer parameters, so these are exposed as continuous parameters.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    integer: bool,
+}
+
+/// The type-specific part of [ParameterMetadata].
+/// Alloy has no color parameters, so these are serialized in the same shape as alloy
+/// parameters, i.e., `{"inner":{"Color":{"current":[r,g,b]}}}`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ParameterMetadataInner {
+    Alloy(alloy::program::ProgramParameter),
+    Color { inner: ColorParameterMetadata },
+}
+
+#[derive(Serialize, Deserialize)]
+enum ColorParameterMetadata {
+    Color { current: [f64; 3] },
+}
+
+/// Parameters of a program, shaped like the metadata returned by the API, including color
+/// parameters, see [Fixture::set_program_parameters].
+#[derive(Deserialize)]
+pub(crate) struct ProgramParametersRequest {
+    parameters: HashMap<String, ParameterMetadataRequest>,
+}
+
+/// The type-specific part of a parameter in a [ProgramParametersRequest], which mirrors
+/// [ParameterMetadataInner].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParameterMetadataRequest {
+    Alloy(alloy::program::ProgramParameter),
+    Color { inner: ColorParameterMetadata },
+}
+
+/// An exported snapshot of the selected program and parameter values of a fixture.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct FixtureState {
+    selected_program: String,
+    /// Parameter values by program name and parameter name.
+    /// Programs without parameters are omitted.
+    #[serde(default)]
+    programs: HashMap<String, HashMap<String, ParameterValue>>,
+}
+
+/// The value of a single parameter, as exported in a [FixtureState].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ParameterValue {
+    Discrete(String),
+    Continuous(f64),
+    Color([f64; 3]),
+}
+
+impl From<ParameterValue> for ParameterRequest {
+    fn from(value: ParameterValue) -> Self {
+        match value {
+            ParameterValue::Discrete(level) => ParameterSetRequest::Discrete { level }.into(),
+            ParameterValue::Continuous(value) => ParameterSetRequest::Continuous { value }.into(),
+            ParameterValue::Color(value) => {
+                ParameterRequest::Color(ColorSetRequest::Color { value })
+            }
+        }
+    }
+}
+
+/// A request to set a parameter value.
+/// This extends [ParameterSetRequest] with values for parameter types alloy doesn't know about.
+/// Alloy requests are accepted unchanged, colors are given as `{"Color":{"value":[r,g,b]}}`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ParameterRequest {
+    Alloy(ParameterSetRequest),
+    Color(ColorSetRequest),
+}
+
+impl From<ParameterSetRequest> for ParameterRequest {
+    fn from(value: ParameterSetRequest) -> Self {
+        ParameterRequest::Alloy(value)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) enum ColorSetRequest {
+    /// Red, green, and blue components, each in [0, 1].
+    Color { value: [f64; 3] },
+}
+
+pub(crate) struct FixtureProgram {
+    name: String,
+    inner: FixtureProgramType,
+    parameter_slugs: SlugIndex,
+    /// Maps output aliases to the index of the parameter controlling that output, for programs
+    /// with one parameter per output.
+    parameter_aliases: HashMap<String, usize>,
+}
+
+impl FixtureProgram {
+    fn new(name: String, inner: FixtureProgramType) -> FixtureProgram {
+        let parameter_aliases = match &inner {
+            FixtureProgramType::BundledManual(p) => p.parameter_aliases(),
+            _ => HashMap::new(),
+        };
+        let parameter_slugs = match &inner {
+            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => {
+                SlugIndex::default()
+            }
+            FixtureProgramType::Lua(p) => {
+                SlugIndex::new(p.parameters.iter().map(|p| p.name.as_str()))
+            }
+            FixtureProgramType::BundledManual(p) => {
+                SlugIndex::new(p.parameters.iter().map(|p| p.name.as_str()))
+            }
+        };
+        if parameter_slugs.has_ambiguous() {
+            warn!(
+                "{}: some parameter names map to the same slug and can only be addressed by their exact name",
+                name
+            );
+        }
+
+        let parameter_names = || parameters_of(&inner).iter().map(|p| p.name.as_str());
+        for (alias, i) in parameter_aliases.iter() {
+            match parameter_slugs.resolve(parameter_names(), alias) {
+                Some(j) if j != *i => warn!(
+                    "{}: alias {} is also the name or slug of another parameter, which takes precedence",
+                    name, alias
+                ),
+                _ => {}
+            }
+        }
+
+        FixtureProgram {
+            name,
+            inner,
+            parameter_slugs,
+            parameter_aliases,
+        }
+    }
+
+    /// Creates an independent instance of this program with the same parameter values, which can
+    /// be run without affecting this one.
+    fn preview_instance(&self, local_time: DateTime<Local>) -> Result<FixtureProgram> {
+        let inner = match &self.inner {
+            FixtureProgramType::BundledConstant(p) => {
+                FixtureProgramType::BundledConstant(p.clone())
+            }
+            FixtureProgramType::BundledManual(p) => FixtureProgramType::BundledManual(p.clone()),
+            FixtureProgramType::External => FixtureProgramType::External,
+            FixtureProgramType::Lua(p) => {
+                FixtureProgramType::Lua(p.reload_with_parameters(local_time)?)
+            }
+        };
+        Ok(FixtureProgram {
+            name: self.name.clone(),
+            inner,
+            parameter_slugs: self.parameter_slugs.clone(),
+            parameter_aliases: self.parameter_aliases.clone(),
+        })
+    }
+
+    /// Loads a Lua program again from its source, see [LuaFixtureProgram::reload].
+    fn reload(&self, local_time: DateTime<Local>) -> Result<FixtureProgram> {
+        match &self.inner {
+            FixtureProgramType::Lua(p) => Ok(FixtureProgram::new(
+                self.name.clone(),
+                FixtureProgramType::Lua(p.reload(local_time)?),
+            )),
+            _ => Err(RuntimeError::BuiltinProgram(self.name.clone()).into()),
+        }
+    }
+
+    /// Takes over parameter values from a previous version of this program, by name.
+    fn migrate_parameters_from(&mut self, previous: &FixtureProgram) {
+        let previous = match &previous.inner {
+            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => return,
+            FixtureProgramType::Lua(p) => &p.parameters,
+            FixtureProgramType::BundledManual(p) => &p.parameters,
+        };
+        match &mut self.inner {
+            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => {}
+            FixtureProgramType::Lua(p) => p.migrate_parameters_from(previous),
+            FixtureProgramType::BundledManual(p) => {
+                migrate_parameters(&mut p.parameters, previous);
+                p.dirty_parameters = true;
+            }
+        }
+    }
+
+    fn enable(&mut self) {
+        match &mut self.inner {
+            FixtureProgramType::BundledConstant(p) => p.enable(),
+            FixtureProgramType::Lua(p) => {
+                if let Err(err) = p.enable() {
+                    warn!("unable to enable program {}: {:?}", self.name, err)
+                }
+            }
+            FixtureProgramType::BundledManual(p) => p.enable(),
+            FixtureProgramType::External => {}
+        }
+    }
+
+    fn disable(&mut self) {
+        match &mut self.inner {
+            FixtureProgramType::Lua(p) => {
+                if let Err(err) = p.disable() {
+                    warn!("unable to disable program {}: {:?}", self.name, err)
+                }
+            }
+            FixtureProgramType::BundledConstant(_)
+            | FixtureProgramType::BundledManual(_)
+            | FixtureProgramType::External => {}
+        }
+    }
+
+    fn run(&mut self, state: &TickState, output_requests: &mut Vec<SetRequest>) -> Result<()> {
+        match &mut self.inner {
+            FixtureProgramType::BundledConstant(p) => p.run(state, output_requests),
+            FixtureProgramType::Lua(p) => p.run(state, output_requests),
+            FixtureProgramType::BundledManual(p) => p.run(state, output_requests),
             FixtureProgramType::External => {
                 // NOP
                 Ok(())
@@ -397,53 +1453,256 @@ impl FixtureProgram {
         }
     }
 
-    pub(crate) fn alloy_metadata(&self) -> alloy::program::ProgramMetadata {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the current value of every parameter, by name.
+    pub(crate) fn parameter_values(&self) -> BTreeMap<String, ParameterValue> {
+        parameters_of(&self.inner)
+            .iter()
+            .map(|p| (p.name.clone(), p.value.current_value()))
+            .collect()
+    }
+
+    pub(crate) fn metadata(&self) -> ProgramMetadata {
         match &self.inner {
             FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => {
-                alloy::program::ProgramMetadata {
+                ProgramMetadata {
                     parameters: Default::default(),
                 }
             }
-            FixtureProgramType::Lua(p) => alloy::program::ProgramMetadata {
+            FixtureProgramType::Lua(p) => ProgramMetadata {
                 parameters: p
                     .parameters
                     .iter()
-                    .map(|p| (p.name.clone(), p.alloy_metadata()))
+                    .map(|p| (p.name.clone(), p.metadata()))
                     .collect(),
             },
-            FixtureProgramType::BundledManual(p) => alloy::program::ProgramMetadata {
+            FixtureProgramType::BundledManual(p) => ProgramMetadata {
                 parameters: p
                     .parameters
                     .iter()
-                    .map(|p| (p.name.clone(), p.alloy_metadata()))
+                    .map(|p| (p.name.clone(), p.metadata()))
                     .collect(),
             },
         }
     }
 
-    pub(crate) fn get_parameter(&self, name: &str) -> Option<&FixtureProgramParameter> {
+    /// Returns the scalar global values of this program's Lua state, for debugging.
+    /// Programs not implemented in Lua have no globals.
+    pub(crate) fn debug_globals(&self) -> Result<BTreeMap<String, serde_json::Value>> {
         match &self.inner {
-            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => None,
-            FixtureProgramType::Lua(p) => p.parameters.iter().find(|param| param.name == name),
-            FixtureProgramType::BundledManual(p) => {
-                p.parameters.iter().find(|param| param.name == name)
+            FixtureProgramType::Lua(p) => p.debug_globals(),
+            _ => Ok(BTreeMap::new()),
+        }
+    }
+
+    fn export_parameters(&self) -> HashMap<String, ParameterValue> {
+        let parameters = match &self.inner {
+            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => {
+                return HashMap::new()
+            }
+            FixtureProgramType::Lua(p) => &p.parameters,
+            FixtureProgramType::BundledManual(p) => &p.parameters,
+        };
+
+        parameters
+            .iter()
+            .map(|p| (p.name.clone(), p.value.current_value()))
+            .collect()
+    }
+
+    /// Returns a copy of the named parameter with the given value set, without modifying the
+    /// program.
+    fn with_parameter_value(
+        &self,
+        parameter_name: &str,
+        value: ParameterRequest,
+    ) -> Result<FixtureProgramParameter> {
+        let mut parameter = self
+            .get_parameter(parameter_name)
+            .ok_or_else(|| RuntimeError::UnknownParameter {
+                program: self.name.clone(),
+                parameter: parameter_name.to_string(),
+            })?
+            .clone();
+        parameter.set(value).context(format!(
+            "invalid value for parameter {} of program {}",
+            parameter_name, self.name
+        ))?;
+
+        Ok(parameter)
+    }
+
+    /// Looks up a parameter by its name, slug, or the alias of the output it controls, in this
+    /// order.
+    pub(crate) fn get_parameter(&self, name: &str) -> Option<&FixtureProgramParameter> {
+        let parameters = parameters_of(&self.inner);
+        self.parameter_slugs
+            .resolve(parameters.iter().map(|p| p.name.as_str()), name)
+            .map(|i| &parameters[i])
+            .or_else(|| self.get_parameter_by_alias(name))
+    }
+
+    /// Looks up the parameter controlling the output with the given alias.
+    /// Only the MANUAL program has parameters for outputs, named after their aliases in the
+    /// universe. Overridden aliases resolve to these as well.
+    pub(crate) fn get_parameter_by_alias(&self, alias: &str) -> Option<&FixtureProgramParameter> {
+        self.parameter_aliases
+            .get(alias)
+            .map(|i| &parameters_of(&self.inner)[*i])
+    }
+
+    /// Sets multiple parameters, by name or slug.
+    /// Values are applied independently, i.e., an invalid value does not prevent the others from
+    /// being applied. Returns the outcome for each requested parameter.
+    pub(crate) fn set_parameters(
+        &mut self,
+        values: BTreeMap<String, ParameterRequest>,
+    ) -> BTreeMap<String, std::result::Result<(), RuntimeError>> {
+        let (parameters, dirty_parameters) = match &mut self.inner {
+            FixtureProgramType::Lua(p) => (&mut p.parameters, &mut p.dirty_parameters),
+            FixtureProgramType::BundledManual(p) => (&mut p.parameters, &mut p.dirty_parameters),
+            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => {
+                return values
+                    .into_keys()
+                    .map(|name| {
+                        let err = RuntimeError::UnknownParameter {
+                            program: self.name.clone(),
+                            parameter: name.clone(),
+                        };
+                        (name, Err(err))
+                    })
+                    .collect()
             }
+        };
+
+        let mut results = BTreeMap::new();
+        for (name, value) in values.into_iter() {
+            let res = match resolve_parameter(
+                &self.parameter_slugs,
+                &self.parameter_aliases,
+                parameters,
+                &name,
+            ) {
+                Some(i) => parameters[i].set(value),
+                None => Err(RuntimeError::UnknownParameter {
+                    program: self.name.clone(),
+                    parameter: name.clone(),
+                }),
+            };
+            results.insert(name, res);
+        }
+
+        // Parameters are injected once for the whole batch, with the next tick.
+        if results.values().any(|res| res.is_ok()) {
+            *dirty_parameters = true;
+        }
+
+        results
+    }
+
+    /// Resets all parameters to the values declared during setup.
+    pub(crate) fn reset_parameters(&mut self) {
+        let (parameters, dirty_parameters) = match &mut self.inner {
+            FixtureProgramType::Lua(p) => (&mut p.parameters, &mut p.dirty_parameters),
+            FixtureProgramType::BundledManual(p) => (&mut p.parameters, &mut p.dirty_parameters),
+            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => return,
+        };
+        for parameter in parameters.iter_mut() {
+            parameter.reset();
         }
+        *dirty_parameters = true;
     }
 
+    /// Looks up a parameter by its name, slug, or the alias of the output it controls, in this
+    /// order.
     pub(crate) fn get_parameter_mut(&mut self, name: &str) -> Option<&mut FixtureProgramParameter> {
-        match &mut self.inner {
-            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => None,
+        let parameters = match &mut self.inner {
+            FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => return None,
             FixtureProgramType::Lua(p) => {
                 p.dirty_parameters = true;
-                p.parameters.iter_mut().find(|param| param.name == name)
+                &mut p.parameters
             }
             FixtureProgramType::BundledManual(p) => {
                 p.dirty_parameters = true;
-                p.parameters.iter_mut().find(|param| param.name == name)
+                &mut p.parameters
             }
+        };
+        resolve_parameter(
+            &self.parameter_slugs,
+            &self.parameter_aliases,
+            parameters,
+            name,
+        )
+        .map(move |i| &mut parameters[i])
+    }
+}
+
+fn parameters_of(program: &FixtureProgramType) -> &[FixtureProgramParameter] {
+    match program {
+        FixtureProgramType::BundledConstant(_) | FixtureProgramType::External => &[],
+        FixtureProgramType::Lua(p) => &p.parameters,
+        FixtureProgramType::BundledManual(p) => &p.parameters,
+    }
+}
+
+/// Resolves a parameter by its exact name, unambiguous slug, or the alias of the output it
+/// controls, in this order.
+/// An alias which is also the name of another parameter thus addresses that parameter, which is
+/// logged when the program is loaded.
+fn resolve_parameter(
+    slugs: &SlugIndex,
+    aliases: &HashMap<String, usize>,
+    parameters: &[FixtureProgramParameter],
+    name: &str,
+) -> Option<usize> {
+    slugs
+        .resolve(parameters.iter().map(|p| p.name.as_str()), name)
+        .or_else(|| aliases.get(name).copied())
+}
+
+/// Maps URL-safe slugs of names to the index of the named item, so that items with names like
+/// "Warm White" can also be addressed as "warm-white".
+#[derive(Clone, Debug, Default)]
+struct SlugIndex {
+    /// Slugs shared by multiple names are ambiguous and map to `None`.
+    slugs: HashMap<String, Option<usize>>,
+}
+
+impl SlugIndex {
+    fn new<'a>(names: impl Iterator<Item = &'a str>) -> SlugIndex {
+        let mut slugs = HashMap::new();
+        for (i, name) in names.enumerate() {
+            slugs
+                .entry(slugify(name))
+                .and_modify(|e| *e = None)
+                .or_insert(Some(i));
         }
+        SlugIndex { slugs }
+    }
+
+    fn has_ambiguous(&self) -> bool {
+        self.slugs.values().any(Option::is_none)
     }
+
+    /// Resolves an exact name or unambiguous slug to the index of the item.
+    /// Exact names take precedence.
+    fn resolve<'a>(&self, mut names: impl Iterator<Item = &'a str>, name: &str) -> Option<usize> {
+        names
+            .position(|n| n == name)
+            .or_else(|| self.slugs.get(name).copied().flatten())
+    }
+}
+
+/// Converts a name to a URL-safe slug, i.e., lowercase alphanumeric words joined by hyphens.
+fn slugify(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
 }
 
 enum FixtureProgramType {
@@ -453,6 +1712,7 @@ enum FixtureProgramType {
     Lua(LuaFixtureProgram),
 }
 
+#[derive(Clone)]
 struct BundledConstantFixtureProgram {
     addresses: HashSet<Address>,
     output_value: OutputValue,
@@ -489,40 +1749,179 @@ impl BundledConstantFixtureProgram {
     }
 }
 
+#[derive(Clone)]
 struct BundledManualFixtureProgram {
     outputs: Vec<Address>,
+    /// All aliases of the outputs, including overridden ones.
+    output_aliases: HashMap<String, Address>,
+    /// One continuous parameter per output, in the same order, optionally followed by the color
+    /// preset parameter.
     parameters: Vec<FixtureProgramParameter>,
+    color_preset: Option<ManualColorPresetState>,
     dirty_parameters: bool,
     reset: bool,
 }
 
+#[derive(Clone)]
+struct ManualColorPresetState {
+    /// Indices of the parameters of the red, green, and blue channels.
+    channels: [usize; 3],
+    colors: Vec<[f64; 3]>,
+    /// The level of the preset parameter which was last applied to the channels.
+    applied_level: usize,
+}
+
 impl BundledManualFixtureProgram {
-    fn new(aliases: HashMap<String, Address>) -> Self {
+    fn new(
+        aliases: HashMap<String, Address>,
+        output_aliases: HashMap<String, Address>,
+        ranges: &HashMap<Address, (f64, f64)>,
+        color_presets: Option<ManualColorPresets>,
+    ) -> Self {
         let mut tmp = aliases.into_iter().collect::<Vec<_>>();
         tmp.sort_by_key(|(_, addr)| *addr);
 
         let addresses = tmp.iter().map(|(_, addr)| *addr).collect();
-        let parameters = tmp
+        let mut parameters: Vec<_> = tmp
             .into_iter()
-            .map(|(alias, _)| alias)
-            .map(|alias| FixtureProgramParameter {
-                name: alias,
-                value: FixtureProgramParameterType::Continuous {
-                    lower_limit_incl: 0.0,
-                    upper_limit_incl: 1.0,
-                    current: 0.0,
-                },
+            .map(|(alias, addr)| {
+                let (lower_limit_incl, upper_limit_incl) =
+                    ranges.get(&addr).copied().unwrap_or((0.0, 1.0));
+                FixtureProgramParameter::new(
+                    alias,
+                    FixtureProgramParameterType::Continuous {
+                        lower_limit_incl,
+                        upper_limit_incl,
+                        current: lower_limit_incl,
+                        step: None,
+                        clamp: false,
+                    },
+                    None,
+                    None,
+                )
             })
             .collect();
 
+        let color_preset = color_presets.map(|color_presets| {
+            // Channels were validated during setup.
+            let channels = color_presets.channels.clone().map(|alias| {
+                parameters
+                    .iter()
+                    .position(|p| p.name == alias)
+                    .expect("missing color channel")
+            });
+            let levels = std::iter::once((
+                MANUAL_COLOR_PRESET_CUSTOM.to_string(),
+                "channels set individually".to_string(),
+            ))
+            .chain(color_presets.presets.iter().map(|(name, [r, g, b])| {
+                (name.clone(), format!("red {}, green {}, blue {}", r, g, b))
+            }))
+            .map(|(name, description)| FixtureProgramParameterDiscreteLevel { name, description })
+            .collect();
+            parameters.push(FixtureProgramParameter::new(
+                MANUAL_COLOR_PRESET_PARAMETER.to_string(),
+                FixtureProgramParameterType::Discrete {
+                    levels,
+                    current_index: 0,
+                },
+                None,
+                None,
+            ));
+
+            ManualColorPresetState {
+                channels,
+                colors: color_presets.presets.into_iter().map(|(_, c)| c).collect(),
+                applied_level: 0,
+            }
+        });
+
         BundledManualFixtureProgram {
             outputs: addresses,
+            output_aliases,
             parameters,
+            color_preset,
             dirty_parameters: true,
             reset: true,
         }
     }
 
+    /// Maps all aliases of the outputs to the index of the parameter of that output.
+    fn parameter_aliases(&self) -> HashMap<String, usize> {
+        self.output_aliases
+            .iter()
+            .filter_map(|(alias, addr)| {
+                let i = self.outputs.iter().position(|a| a == addr)?;
+                Some((alias.clone(), i))
+            })
+            .collect()
+    }
+
+    /// Writes a newly selected color preset to the color channels.
+    /// If the channels were changed individually instead, the preset is reset to custom.
+    fn apply_color_preset(&mut self) {
+        let Some(preset) = &mut self.color_preset else {
+            return;
+        };
+        let preset_parameter = self.parameters.len() - 1;
+        let FixtureProgramParameterType::Discrete { current_index, .. } =
+            self.parameters[preset_parameter].value
+        else {
+            panic!("color preset parameter is not discrete")
+        };
+
+        let new_index = if current_index != preset.applied_level {
+            if current_index != 0 {
+                let color = preset.colors[current_index - 1];
+                for (channel, value) in preset.channels.iter().zip(color) {
+                    if let FixtureProgramParameterType::Continuous {
+                        lower_limit_incl,
+                        upper_limit_incl,
+                        current,
+                        ..
+                    } = &mut self.parameters[*channel].value
+                    {
+                        *current =
+                            scale_color_component(value, *lower_limit_incl, *upper_limit_incl);
+                    }
+                    self.parameters[*channel].touch();
+                }
+            }
+            // Switching to custom keeps the current channel values.
+            current_index
+        } else if current_index != 0 {
+            let color = preset.colors[current_index - 1];
+            let unchanged = preset.channels.iter().zip(color).all(|(channel, value)| {
+                matches!(
+                    self.parameters[*channel].value,
+                    FixtureProgramParameterType::Continuous {
+                        lower_limit_incl,
+                        upper_limit_incl,
+                        current,
+                        ..
+                    } if current == scale_color_component(value, lower_limit_incl, upper_limit_incl)
+                )
+            });
+            if unchanged {
+                current_index
+            } else {
+                0
+            }
+        } else {
+            0
+        };
+
+        preset.applied_level = new_index;
+        if new_index != current_index {
+            if let FixtureProgramParameterType::Discrete { current_index, .. } =
+                &mut self.parameters[preset_parameter].value
+            {
+                *current_index = new_index;
+            }
+            self.parameters[preset_parameter].touch();
+        }
+    }
+
     fn enable(&mut self) {
         self.reset = true
     }
@@ -540,15 +1939,29 @@ impl BundledManualFixtureProgram {
         self.reset = false;
         self.dirty_parameters = false;
 
+        self.apply_color_preset();
+
         // Build output requests from parameter values.
+        // This skips the color preset parameter, which has no output.
         output_requests.extend(self.outputs.iter().zip(self.parameters.iter()).map(
             |(addr, param)| match param.value {
                 FixtureProgramParameterType::Discrete { .. } => {
                     panic!("discrete parameter in builtin manual program")
                 }
-                FixtureProgramParameterType::Continuous { current, .. } => SetRequest {
+                FixtureProgramParameterType::Integer { .. } => {
+                    panic!("integer parameter in builtin manual program")
+                }
+                FixtureProgramParameterType::Color { .. } => {
+                    panic!("color parameter in builtin manual program")
+                }
+                FixtureProgramParameterType::Continuous {
+                    lower_limit_incl,
+                    upper_limit_incl,
+                    current,
+                    ..
+                } => SetRequest {
                     target: SetRequestTarget::Address(*addr),
-                    value: alloy::map_to_value((0.0, 1.0), current),
+                    value: alloy::map_to_value((lower_limit_incl, upper_limit_incl), current),
                 },
             },
         ));
@@ -557,44 +1970,67 @@ impl BundledManualFixtureProgram {
     }
 }
 
+/// Scales a color component in `[0, 1]` to the range of a MANUAL channel.
+fn scale_color_component(value: f64, lower_limit_incl: f64, upper_limit_incl: f64) -> f64 {
+    lower_limit_incl + value * (upper_limit_incl - lower_limit_incl)
+}
+
 struct LuaFixtureProgram {
     parameters: Vec<FixtureProgramParameter>,
-    slow_mode: bool,
+    /// Number of ticks to skip between runs, if the program runs in slow mode.
+    slow_mode: Option<usize>,
     skip_ticks_until_next_run: usize,
     dirty_parameters: bool,
     lua: Lua,
     epoch: Instant,
+    /// What the program was loaded from, to load it again for previews.
+    source_path: PathBuf,
+    output_aliases: HashMap<String, Address>,
+    rng_seed: u64,
+    selected_programs: SelectedPrograms,
 }
 
 impl LuaFixtureProgram {
     fn new<P: AsRef<Path>>(
         source: P,
         output_aliases: HashMap<String, Address>,
-        time_of_day: u32,
+        local_time: DateTime<Local>,
+        rng_seed: u64,
+        selected_programs: SelectedPrograms,
     ) -> Result<Self> {
-        let lua = Lua::new();
+        let lua = new_sandboxed_lua()?;
         debug!("loading program at {:?}...", source.as_ref());
         let program_source = fs::read_to_string(source.as_ref())?;
         let program_epoch = Instant::now();
 
-        lua.load_from_std_lib(mlua::StdLib::TABLE)?;
-
         lua.load(PROGRAM_BUILTIN_SOURCE)
             .exec()
             .expect("unable to load program builtin source");
 
         // Inject a bunch of constants after builtins were loaded, but before the program source
         // is loaded.
-        Self::inject_pre_load_constants(&lua, program_epoch, output_aliases)?;
+        Self::inject_pre_load_constants(
+            &lua,
+            program_epoch,
+            output_aliases.clone(),
+            rng_seed,
+            selected_programs.clone(),
+        )?;
 
         // Load program source.
         lua.load(&program_source).exec()?;
 
         // Check source version
         let source_version: u16 = lua.globals().get("SOURCE_VERSION")?;
-        ensure!(source_version == VERSION, "source version mismatch");
+        ensure!(
+            source_version == VERSION,
+            RuntimeError::VersionMismatch {
+                expected: VERSION,
+                actual: source_version,
+            }
+        );
 
-        let setup_values = Self::setup(&lua, time_of_day).context("unable to set up program")?;
+        let setup_values = Self::setup(&lua, &local_time).context("unable to set up program")?;
         debug!(
             "set up program at {:?}: {:?}",
             source.as_ref(),
@@ -608,14 +2044,70 @@ impl LuaFixtureProgram {
             lua,
             epoch: program_epoch,
             dirty_parameters: true,
+            source_path: source.as_ref().to_path_buf(),
+            output_aliases,
+            rng_seed,
+            selected_programs,
         })
     }
 
+    /// Loads the program again from its source, with the current parameter values.
+    /// Lua state can't be copied, so this is how independent instances are created.
+    fn reload_with_parameters(&self, local_time: DateTime<Local>) -> Result<Self> {
+        let mut program = Self::new(
+            &self.source_path,
+            self.output_aliases.clone(),
+            local_time,
+            self.rng_seed,
+            self.selected_programs.clone(),
+        )?;
+        program.parameters = self.parameters.clone();
+        Ok(program)
+    }
+
+    /// Loads the program again from its source, e.g., after it was edited.
+    /// Parameters which still exist keep their values, see
+    /// [FixtureProgramParameter::migrate_from], all others take their declared defaults.
+    fn reload(&self, local_time: DateTime<Local>) -> Result<Self> {
+        let mut program = Self::new(
+            &self.source_path,
+            self.output_aliases.clone(),
+            local_time,
+            self.rng_seed,
+            self.selected_programs.clone(),
+        )?;
+        program.migrate_parameters_from(&self.parameters);
+        Ok(program)
+    }
+
+    fn migrate_parameters_from(&mut self, previous: &[FixtureProgramParameter]) {
+        migrate_parameters(&mut self.parameters, previous);
+        self.dirty_parameters = true;
+    }
+
     fn inject_pre_load_constants(
         lua: &Lua,
         epoch: Instant,
         output_aliases: HashMap<String, Address>,
+        rng_seed: u64,
+        selected_programs: SelectedPrograms,
     ) -> Result<()> {
+        // Resolves `<alias>_0` to `<alias>_<count-1>` to addresses, for multi-channel fixtures.
+        let channel_aliases = output_aliases.clone();
+        lua.globals().set(
+            "channels",
+            lua.create_function(move |_, (alias, count): (String, usize)| {
+                (0..count)
+                    .map(|i| {
+                        let channel = format!("{}_{}", alias, i);
+                        channel_aliases.get(&channel).copied().ok_or_else(|| {
+                            mlua::Error::external(format!("unknown output alias: {}", channel))
+                        })
+                    })
+                    .collect::<mlua::Result<Vec<Address>>>()
+            })?,
+        )?;
+
         lua.globals()
             .set("output_alias_address", output_aliases)
             .context("unable to set output alias mappings")?;
@@ -638,23 +2130,79 @@ impl LuaFixtureProgram {
             })?,
         )?;
 
+        // Provide read-only access to the selected programs of other fixtures.
+        lua.globals().set(
+            "get_fixture_program",
+            lua.create_function(move |_, fixture_name: String| {
+                selected_programs
+                    .read()
+                    .unwrap()
+                    .get(&fixture_name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        mlua::Error::external(format!("unknown fixture: {}", fixture_name))
+                    })
+            })?,
+        )?;
+
+        // Inject easing functions.
+        easing::register(lua).context("unable to register easing functions")?;
+
+        // Inject color conversion functions.
+        color::register(lua).context("unable to register color functions")?;
+
+        // Inject a seeded RNG, which lives as long as the program.
+        // Unlike math.random, this produces the same sequence every time the program is loaded.
+        let mut rng = Pcg64Mcg::seed_from_u64(rng_seed);
+        lua.globals().set(
+            "rand",
+            lua.create_function_mut(move |_, ()| Ok(rng.gen::<f64>()))?,
+        )?;
+
         Ok(())
     }
 
-    fn inject_environment(lua: &Lua, time_of_day: u32) -> Result<()> {
-        lua.globals()
+    fn inject_environment(
+        lua: &Lua,
+        local_time: &DateTime<Local>,
+        sun_times: Option<SunTimes>,
+    ) -> Result<()> {
+        let globals = lua.globals();
+        let time_of_day =
+            local_time.hour() * 60 * 60 + local_time.minute() * 60 + local_time.second();
+        globals
             .set("TIME_OF_DAY", time_of_day)
             .context("unable to set time of day")?;
+        globals
+            .set("DAY_OF_WEEK", local_time.weekday().num_days_from_sunday())
+            .context("unable to set day of week")?;
+        globals
+            .set("DAY_OF_MONTH", local_time.day())
+            .context("unable to set day of month")?;
+        globals
+            .set("MONTH", local_time.month())
+            .context("unable to set month")?;
+        globals
+            .set("DAY_OF_YEAR", local_time.ordinal())
+            .context("unable to set day of year")?;
+        globals
+            .set("SUNRISE", sun_times.map(|t| t.sunrise))
+            .context("unable to set sunrise")?;
+        globals
+            .set("SUNSET", sun_times.map(|t| t.sunset))
+            .context("unable to set sunset")?;
 
         Ok(())
     }
 
-    fn setup(lua: &Lua, time_of_day: u32) -> Result<ProgramSetupValues> {
-        let mut slow_mode = false;
+    fn setup(lua: &Lua, local_time: &DateTime<Local>) -> Result<ProgramSetupValues> {
+        let mut slow_mode = None;
+        let mut clamp_parameters = false;
         let mut parameters: Vec<FixtureProgramParameter> = Vec::new();
 
         // Inject inputs
-        Self::inject_environment(lua, time_of_day).context("unable to inject environment")?;
+        // Sunrise and sunset are not known during setup, since they require a location.
+        Self::inject_environment(lua, local_time, None).context("unable to inject environment")?;
 
         // Run setup
         let globals = lua.globals();
@@ -677,20 +2225,82 @@ impl LuaFixtureProgram {
                     }
 
                     let param_type: String = parameter_table.get("_type")?;
+                    let unit: Option<String> = parameter_table.get("_unit")?;
+                    let display: Option<String> = parameter_table.get("_display")?;
                     match param_type.as_str() {
                         PARAMETER_TYPE_CONTINUOUS => {
                             let lower: f64 = parameter_table.get("_lower")?;
                             let upper: f64 = parameter_table.get("_upper")?;
                             let default: f64 = parameter_table.get("_default")?;
+                            let step: Option<f64> = parameter_table.get("_step")?;
+                            if let Some(step) = step {
+                                if !(step.is_finite() && step > 0.0) {
+                                    return Err(mlua::Error::external(anyhow!(
+                                        "step {} of continuous parameter {} is not positive",
+                                        step,
+                                        param_name
+                                    )));
+                                }
+                            }
 
-                            parameters.push(FixtureProgramParameter {
-                                name: param_name,
-                                value: FixtureProgramParameterType::Continuous {
+                            parameters.push(FixtureProgramParameter::new(
+                                param_name,
+                                FixtureProgramParameterType::Continuous {
                                     lower_limit_incl: lower,
                                     upper_limit_incl: upper,
                                     current: default,
+                                    step,
+                                    clamp: false,
                                 },
-                            });
+                                unit,
+                                display,
+                            ));
+                        }
+                        PARAMETER_TYPE_INTEGER => {
+                            let lower: i64 = parameter_table.get("_lower")?;
+                            let upper: i64 = parameter_table.get("_upper")?;
+                            let default: i64 = parameter_table.get("_default")?;
+                            if !(lower <= default && default <= upper) {
+                                return Err(mlua::Error::external(anyhow!(
+                                    "default value {} of integer parameter {} is out of range [{}, {}]",
+                                    default,
+                                    param_name,
+                                    lower,
+                                    upper
+                                )));
+                            }
+
+                            parameters.push(FixtureProgramParameter::new(
+                                param_name,
+                                FixtureProgramParameterType::Integer {
+                                    lower_limit_incl: lower,
+                                    upper_limit_incl: upper,
+                                    current: default,
+                                },
+                                unit,
+                                display,
+                            ));
+                        }
+                        PARAMETER_TYPE_COLOR => {
+                            let default = [
+                                parameter_table.get("_r")?,
+                                parameter_table.get("_g")?,
+                                parameter_table.get("_b")?,
+                            ];
+                            if let Some(c) = default.iter().find(|c| !(0.0..=1.0).contains(*c)) {
+                                return Err(mlua::Error::external(anyhow!(
+                                    "default component {} of color parameter {} is out of range [0, 1]",
+                                    c,
+                                    param_name
+                                )));
+                            }
+
+                            parameters.push(FixtureProgramParameter::new(
+                                param_name,
+                                FixtureProgramParameterType::Color { current: default },
+                                unit,
+                                display,
+                            ));
                         }
                         PARAMETER_TYPE_DISCRETE => {
                             let num_levels: usize = parameter_table.get("_i")?;
@@ -715,13 +2325,52 @@ impl LuaFixtureProgram {
                                 )));
                             }
 
-                            parameters.push(FixtureProgramParameter {
-                                name: param_name,
-                                value: FixtureProgramParameterType::Discrete {
+                            // Defaults to the first level.
+                            // The default level is given either by name or by its index.
+                            let default: mlua::Value = parameter_table.get("_default")?;
+                            let current_index = match default {
+                                mlua::Value::Nil => 0,
+                                mlua::Value::Integer(index) => usize::try_from(index)
+                                    .ok()
+                                    .filter(|i| *i < levels.len())
+                                    .ok_or_else(|| {
+                                        mlua::Error::external(anyhow!(
+                                            "default level index {} of discrete parameter {} is out of range [0, {})",
+                                            index,
+                                            param_name,
+                                            levels.len()
+                                        ))
+                                    })?,
+                                mlua::Value::String(default) => {
+                                    let default = default.to_str()?;
+                                    levels.iter().position(|l| l.name == default).ok_or_else(
+                                        || {
+                                            mlua::Error::external(anyhow!(
+                                                "default level {} of discrete parameter {} is not a level",
+                                                default,
+                                                param_name
+                                            ))
+                                        },
+                                    )?
+                                }
+                                other => {
+                                    return Err(mlua::Error::external(anyhow!(
+                                        "default level of discrete parameter {} must be a level name or index, got {}",
+                                        param_name,
+                                        other.type_name()
+                                    )))
+                                }
+                            };
+
+                            parameters.push(FixtureProgramParameter::new(
+                                param_name,
+                                FixtureProgramParameterType::Discrete {
                                     levels,
-                                    current_index: 0,
+                                    current_index,
                                 },
-                            });
+                                unit,
+                                display,
+                            ));
                         }
                         _ => {
                             return Err(mlua::Error::external(anyhow!(
@@ -735,18 +2384,45 @@ impl LuaFixtureProgram {
                 })?;
             globals.set("_declare_parameter_generic", declare_parameter_generic)?;
 
-            let set_slow_mode = scope.create_function_mut(|_, p_slow_mode| {
-                slow_mode = p_slow_mode;
+            // Accepts either a boolean, or the interval in ticks at which to run the program.
+            let set_slow_mode = scope.create_function_mut(|_, p_slow_mode: mlua::Value| {
+                slow_mode = match p_slow_mode {
+                    mlua::Value::Boolean(false) => None,
+                    mlua::Value::Boolean(true) => Some(SLOW_MODE_NUM_SKIP_TICKS),
+                    mlua::Value::Integer(interval) if interval >= 1 => {
+                        Some(interval as usize - 1)
+                    }
+                    other => {
+                        return Err(mlua::Error::external(format!(
+                            "slow mode must be a boolean or a positive integer number of ticks, got {:?}",
+                            other
+                        )))
+                    }
+                };
                 Ok(())
             })?;
             globals.set("set_slow_mode", set_slow_mode)?;
 
+            let set_clamp_parameters = scope.create_function_mut(|_, to: bool| {
+                clamp_parameters = to;
+                Ok(())
+            })?;
+            globals.set("set_clamp_parameters", set_clamp_parameters)?;
+
             // Actually call setup
             setup.call(())?;
 
             Ok(())
         })?;
 
+        for parameter in parameters.iter_mut() {
+            for value in [&mut parameter.value, &mut parameter.default] {
+                if let FixtureProgramParameterType::Continuous { clamp, .. } = value {
+                    *clamp = clamp_parameters;
+                }
+            }
+        }
+
         Ok(ProgramSetupValues {
             parameters,
             slow_mode,
@@ -775,6 +2451,12 @@ impl LuaFixtureProgram {
                     FixtureProgramParameterType::Continuous { current, .. } => {
                         current.into_lua(&self.lua)
                     }
+                    FixtureProgramParameterType::Integer { current, .. } => {
+                        current.into_lua(&self.lua)
+                    }
+                    FixtureProgramParameterType::Color { current } => {
+                        current.to_vec().into_lua(&self.lua)
+                    }
                 }
                 .map(|v| (p.name.clone(), v))
             })
@@ -790,8 +2472,53 @@ impl LuaFixtureProgram {
         Ok(())
     }
 
-    fn enable(&mut self) {
-        self.skip_ticks_until_next_run = 0
+    fn enable(&mut self) -> Result<()> {
+        self.skip_ticks_until_next_run = 0;
+
+        // Make current parameter values available to the hook.
+        self.inject_parameters()
+            .context("unable to inject parameters")?;
+        self.call_hook("on_enable")
+    }
+
+    fn disable(&mut self) -> Result<()> {
+        self.call_hook("on_disable")
+    }
+
+    /// Calls the global function with the given name, if the program defines it.
+    fn call_hook(&self, name: &str) -> Result<()> {
+        let hook: Option<Function> = self.lua.globals().get(name)?;
+        if let Some(hook) = hook {
+            hook.call::<_, ()>(())
+                .context(format!("failed to execute {}", name))?;
+        }
+
+        Ok(())
+    }
+
+    fn debug_globals(&self) -> Result<BTreeMap<String, serde_json::Value>> {
+        let mut globals = BTreeMap::new();
+        for pair in self.lua.globals().pairs::<mlua::Value, mlua::Value>() {
+            let (key, value) = pair.context("unable to iterate globals")?;
+            let key = match key {
+                mlua::Value::String(s) => s.to_str()?.to_string(),
+                _ => continue,
+            };
+            // Skip functions, tables, and other non-scalar values.
+            let value = match value {
+                mlua::Value::Boolean(b) => serde_json::Value::from(b),
+                mlua::Value::Integer(i) => serde_json::Value::from(i),
+                mlua::Value::Number(n) => match serde_json::Number::from_f64(n) {
+                    Some(n) => serde_json::Value::Number(n),
+                    None => continue,
+                },
+                mlua::Value::String(s) => serde_json::Value::from(s.to_string_lossy().to_string()),
+                _ => continue,
+            };
+            globals.insert(key, value);
+        }
+
+        Ok(globals)
     }
 
     fn run(&mut self, state: &TickState, output_requests: &mut Vec<SetRequest>) -> Result<()> {
@@ -801,13 +2528,10 @@ impl LuaFixtureProgram {
                 .context("unable to inject parameters")?;
 
             // Inject environment
-            let time_of_day = state.local_time.hour() * 60 * 60
-                + state.local_time.minute() * 60
-                + state.local_time.second();
-            Self::inject_environment(&self.lua, time_of_day)?;
+            Self::inject_environment(&self.lua, &state.local_time, state.sun_times)?;
 
             // Run tick
-            let output_values_by_address: mlua::Result<HashMap<Address, OutputValue>> = {
+            let output_values: mlua::Result<TickResult> = {
                 let globals = self.lua.globals();
 
                 let now = state.timestamp.duration_since(self.epoch).as_secs_f64();
@@ -815,16 +2539,28 @@ impl LuaFixtureProgram {
 
                 tick.call(now)
             };
-            debug!("_tick returned {:?}", output_values_by_address);
-
-            let output_values = output_values_by_address.context("failed to execute _tick")?;
+            debug!("_tick returned {:?}", output_values);
+
+            let (mut output_values, returned_values) =
+                output_values.context("failed to execute _tick")?;
+            // Values returned from tick override those set via set_alias.
+            for (key, val) in returned_values.into_iter().flatten() {
+                let addr = match key {
+                    OutputKey::Address(addr) => addr,
+                    OutputKey::Alias(alias) => *self
+                        .output_aliases
+                        .get(&alias)
+                        .ok_or_else(|| anyhow!("unknown output alias: {}", alias))?,
+                };
+                output_values.insert(addr, val);
+            }
             output_requests.extend(output_values.into_iter().map(|(addr, val)| SetRequest {
                 value: val,
                 target: SetRequestTarget::Address(addr),
             }));
 
-            if self.slow_mode {
-                self.skip_ticks_until_next_run = SLOW_MODE_NUM_SKIP_TICKS;
+            if let Some(skip_ticks) = self.slow_mode {
+                self.skip_ticks_until_next_run = skip_ticks;
             }
         } else {
             self.skip_ticks_until_next_run -= 1;
@@ -834,20 +2570,130 @@ impl LuaFixtureProgram {
     }
 }
 
+/// Values set via `set_alias`, and the table returned by `tick`, if it returned one.
+type TickResult = (
+    HashMap<Address, OutputValue>,
+    Option<HashMap<OutputKey, OutputValue>>,
+);
+
+/// A key of the table returned by `tick`, either an address or an output alias.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum OutputKey {
+    Address(Address),
+    Alias(String),
+}
+
+impl<'lua> FromLua<'lua> for OutputKey {
+    fn from_lua(value: mlua::Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        match value {
+            mlua::Value::String(s) => Ok(OutputKey::Alias(s.to_str()?.to_string())),
+            value => Address::from_lua(value, lua).map(OutputKey::Address),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct ProgramSetupValues {
     parameters: Vec<FixtureProgramParameter>,
-    slow_mode: bool,
+    slow_mode: Option<usize>,
 }
 
 #[derive(Clone, Debug, Serialize)]
 pub(crate) struct FixtureProgramParameter {
     name: String,
     value: FixtureProgramParameterType,
+    /// The value as declared during setup, to reset to.
+    #[serde(skip)]
+    default: FixtureProgramParameterType,
+    /// Increases with every change of the value, across all parameters.
+    #[serde(skip)]
+    revision: u64,
+    /// The unit of the parameter value, e.g., "Hz" or "seconds".
+    unit: Option<String>,
+    /// A hint for UIs on how to display the parameter, e.g., "percent".
+    display: Option<String>,
+}
+
+/// Takes over values from previous versions of parameters, by name.
+/// See [FixtureProgramParameter::migrate_from].
+fn migrate_parameters(
+    parameters: &mut [FixtureProgramParameter],
+    previous: &[FixtureProgramParameter],
+) {
+    for parameter in parameters.iter_mut() {
+        if let Some(previous) = previous.iter().find(|p| p.name == parameter.name) {
+            if !parameter.migrate_from(previous) {
+                debug!(
+                    "resetting parameter {} to its default, since its type or range changed",
+                    parameter.name
+                );
+            }
+        }
+    }
 }
 
 impl FixtureProgramParameter {
-    pub(crate) fn alloy_metadata(&self) -> alloy::program::ProgramParameter {
+    fn new(
+        name: String,
+        value: FixtureProgramParameterType,
+        unit: Option<String>,
+        display: Option<String>,
+    ) -> FixtureProgramParameter {
+        FixtureProgramParameter {
+            name,
+            default: value.clone(),
+            value,
+            revision: next_parameter_revision(),
+            unit,
+            display,
+        }
+    }
+
+    /// Marks the value as changed.
+    fn touch(&mut self) {
+        self.revision = next_parameter_revision();
+    }
+
+    /// Resets the value to the one declared during setup.
+    fn reset(&mut self) {
+        self.value = self.default.clone();
+        self.touch();
+    }
+
+    /// Takes over the value of a previous version of this parameter, if it has the same type and
+    /// the value is still valid, e.g., within the new range.
+    /// Returns whether the value was taken over.
+    fn migrate_from(&mut self, previous: &FixtureProgramParameter) -> bool {
+        mem::discriminant(&self.value) == mem::discriminant(&previous.value)
+            && self
+                .value
+                .set(previous.value.current_value().into())
+                .is_ok()
+    }
+
+    pub(crate) fn metadata(&self) -> ParameterMetadata {
+        ParameterMetadata {
+            inner: match &self.value {
+                FixtureProgramParameterType::Color { current } => ParameterMetadataInner::Color {
+                    inner: ColorParameterMetadata::Color { current: *current },
+                },
+                _ => ParameterMetadataInner::Alloy(self.alloy_metadata()),
+            },
+            unit: self.unit.clone(),
+            step: match &self.value {
+                FixtureProgramParameterType::Continuous { step, .. } => *step,
+                _ => None,
+            },
+            display: self.display.clone(),
+            default: self.default.current_value(),
+            revision: self.revision,
+            integer: matches!(self.value, FixtureProgramParameterType::Integer { .. }),
+        }
+    }
+
+    /// Returns the alloy representation of this parameter.
+    /// Panics for color parameters, which alloy can not represent.
+    fn alloy_metadata(&self) -> alloy::program::ProgramParameter {
         match &self.value {
             FixtureProgramParameterType::Discrete {
                 levels,
@@ -873,6 +2719,7 @@ impl FixtureProgramParameter {
                 lower_limit_incl,
                 upper_limit_incl,
                 current,
+                ..
             } => alloy::program::ProgramParameter {
                 inner: alloy::program::ParameterType::Continuous {
                     lower_limit_incl: *lower_limit_incl,
@@ -880,15 +2727,46 @@ impl FixtureProgramParameter {
                     current: *current,
                 },
             },
+            FixtureProgramParameterType::Integer {
+                lower_limit_incl,
+                upper_limit_incl,
+                current,
+            } => alloy::program::ProgramParameter {
+                inner: alloy::program::ParameterType::Continuous {
+                    lower_limit_incl: *lower_limit_incl as f64,
+                    upper_limit_incl: *upper_limit_incl as f64,
+                    current: *current as f64,
+                },
+            },
+            FixtureProgramParameterType::Color { .. } => {
+                panic!("color parameter has no alloy representation")
+            }
         }
     }
 
-    pub(crate) fn set(&mut self, to: ParameterSetRequest) -> Result<()> {
-        self.value.set(to)
+    pub(crate) fn set(&mut self, to: ParameterRequest) -> std::result::Result<(), RuntimeError> {
+        self.value.set(to)?;
+        self.touch();
+        Ok(())
     }
 
-    pub(crate) fn cycle(&mut self) -> Result<String> {
-        self.value.cycle()
+    /// Cycles to the next value, or the previous one if `backwards` is set, and returns it.
+    /// See [FixtureProgramParameterType::cycle].
+    pub(crate) fn cycle(&mut self, backwards: bool) -> std::result::Result<String, RuntimeError> {
+        let value = self.value.cycle(backwards)?;
+        self.touch();
+        Ok(value)
+    }
+
+    /// Changes the value by the given delta and returns the new value.
+    /// See [FixtureProgramParameterType::increment].
+    pub(crate) fn increment(
+        &mut self,
+        delta: f64,
+    ) -> std::result::Result<ParameterValue, RuntimeError> {
+        let value = self.value.increment(delta)?;
+        self.touch();
+        Ok(value)
     }
 }
 
@@ -902,64 +2780,861 @@ enum FixtureProgramParameterType {
         lower_limit_incl: f64,
         upper_limit_incl: f64,
         current: f64,
+        /// A hint for UIs on the granularity of the value, e.g., for sliders.
+        step: Option<f64>,
+        /// Whether values out of range are clamped to the nearest limit instead of rejected.
+        clamp: bool,
     },
+    Integer {
+        lower_limit_incl: i64,
+        upper_limit_incl: i64,
+        current: i64,
+    },
+    /// An RGB color, each component in [0, 1].
+    Color { current: [f64; 3] },
 }
 
 impl FixtureProgramParameterType {
-    fn set(&mut self, to: ParameterSetRequest) -> Result<()> {
+    fn current_value(&self) -> ParameterValue {
+        match self {
+            FixtureProgramParameterType::Discrete {
+                levels,
+                current_index,
+            } => ParameterValue::Discrete(levels.get(*current_index).unwrap().name.clone()),
+            FixtureProgramParameterType::Continuous { current, .. } => {
+                ParameterValue::Continuous(*current)
+            }
+            FixtureProgramParameterType::Integer { current, .. } => {
+                ParameterValue::Continuous(*current as f64)
+            }
+            FixtureProgramParameterType::Color { current } => ParameterValue::Color(*current),
+        }
+    }
+
+    fn set(&mut self, to: ParameterRequest) -> std::result::Result<(), RuntimeError> {
         match self {
             FixtureProgramParameterType::Discrete {
                 levels,
                 current_index,
             } => {
-                if let ParameterSetRequest::Discrete { level } = to {
+                if let ParameterRequest::Alloy(ParameterSetRequest::Discrete { level }) = to {
                     if let Some(index) = levels.iter().position(|l| &l.name == &level) {
                         *current_index = index;
                         Ok(())
                     } else {
-                        bail!("level not found")
+                        Err(RuntimeError::UnknownLevel(level))
                     }
                 } else {
-                    bail!("continuous value supplied to discrete parameter")
+                    Err(RuntimeError::TypeMismatch(
+                        "non-discrete value supplied to discrete parameter",
+                    ))
                 }
             }
             FixtureProgramParameterType::Continuous {
                 lower_limit_incl,
                 upper_limit_incl,
                 current,
+                clamp,
+                ..
             } => {
-                if let ParameterSetRequest::Continuous { value } = to {
-                    ensure!(
-                        value <= *upper_limit_incl && value >= *lower_limit_incl,
-                        "value is out of range"
-                    );
+                if let ParameterRequest::Alloy(ParameterSetRequest::Continuous { mut value }) = to {
+                    if *clamp && !value.is_nan() {
+                        let clamped = value.clamp(*lower_limit_incl, *upper_limit_incl);
+                        if clamped != value {
+                            debug!(
+                                "clamping value {} to [{}, {}]",
+                                value, lower_limit_incl, upper_limit_incl
+                            );
+                            value = clamped;
+                        }
+                    }
+                    if !(value <= *upper_limit_incl && value >= *lower_limit_incl) {
+                        return Err(RuntimeError::OutOfRange {
+                            value,
+                            lower: *lower_limit_incl,
+                            upper: *upper_limit_incl,
+                        });
+                    }
+                    *current = value;
+                    Ok(())
+                } else {
+                    Err(RuntimeError::TypeMismatch(
+                        "non-continuous value supplied to continuous parameter",
+                    ))
+                }
+            }
+            FixtureProgramParameterType::Integer {
+                lower_limit_incl,
+                upper_limit_incl,
+                current,
+            } => {
+                if let ParameterRequest::Alloy(ParameterSetRequest::Continuous { value }) = to {
+                    // Integer parameters look continuous to clients, e.g., sliders.
+                    let value = value.round();
+                    if !(value <= *upper_limit_incl as f64 && value >= *lower_limit_incl as f64) {
+                        return Err(RuntimeError::OutOfRange {
+                            value,
+                            lower: *lower_limit_incl as f64,
+                            upper: *upper_limit_incl as f64,
+                        });
+                    }
+                    *current = value as i64;
+                    Ok(())
+                } else {
+                    Err(RuntimeError::TypeMismatch(
+                        "non-continuous value supplied to integer parameter",
+                    ))
+                }
+            }
+            FixtureProgramParameterType::Color { current } => {
+                if let ParameterRequest::Color(ColorSetRequest::Color { value }) = to {
+                    if let Some(c) = value.iter().find(|c| !(0.0..=1.0).contains(*c)) {
+                        return Err(RuntimeError::OutOfRange {
+                            value: *c,
+                            lower: 0.0,
+                            upper: 1.0,
+                        });
+                    }
                     *current = value;
                     Ok(())
                 } else {
-                    bail!("discrete value supplied to continuous parameter")
+                    Err(RuntimeError::TypeMismatch(
+                        "non-color value supplied to color parameter",
+                    ))
                 }
             }
         }
     }
 
-    fn cycle(&mut self) -> Result<String> {
+    /// Steps discrete and integer values by one, wrapping around in either direction.
+    fn cycle(&mut self, backwards: bool) -> std::result::Result<String, RuntimeError> {
+        match self {
+            FixtureProgramParameterType::Continuous { .. } => Err(RuntimeError::TypeMismatch(
+                "continuous parameter can not be cycled",
+            )),
+            FixtureProgramParameterType::Color { .. } => Err(RuntimeError::TypeMismatch(
+                "color parameter can not be cycled",
+            )),
+            FixtureProgramParameterType::Discrete {
+                levels,
+                current_index,
+            } => step_level(levels, current_index, if backwards { -1 } else { 1 }),
+            FixtureProgramParameterType::Integer {
+                lower_limit_incl,
+                upper_limit_incl,
+                current,
+            } => {
+                *current = if backwards {
+                    if *current <= *lower_limit_incl {
+                        *upper_limit_incl
+                    } else {
+                        *current - 1
+                    }
+                } else if *current >= *upper_limit_incl {
+                    *lower_limit_incl
+                } else {
+                    *current + 1
+                };
+                Ok(current.to_string())
+            }
+        }
+    }
+
+    /// Changes the value by the given delta.
+    /// Continuous and integer values are clamped to their range, with integer deltas being
+    /// rounded.
+    /// Discrete parameters step through their levels by the delta, which must be integral,
+    /// wrapping around in both directions.
+    fn increment(&mut self, delta: f64) -> std::result::Result<ParameterValue, RuntimeError> {
         match self {
-            FixtureProgramParameterType::Continuous { .. } => {
-                bail!("continuous parameter can not be cycled")
+            FixtureProgramParameterType::Continuous {
+                lower_limit_incl,
+                upper_limit_incl,
+                current,
+                ..
+            } => {
+                *current = (*current + delta).clamp(*lower_limit_incl, *upper_limit_incl);
+            }
+            FixtureProgramParameterType::Integer {
+                lower_limit_incl,
+                upper_limit_incl,
+                current,
+            } => {
+                *current = current
+                    .saturating_add(delta.round() as i64)
+                    .clamp(*lower_limit_incl, *upper_limit_incl);
             }
             FixtureProgramParameterType::Discrete {
                 levels,
                 current_index,
             } => {
-                *current_index = (*current_index + 1) % levels.len();
-                Ok(levels[*current_index].name.clone())
+                if delta.fract() != 0.0 {
+                    return Err(RuntimeError::TypeMismatch(
+                        "non-integer delta supplied to discrete parameter",
+                    ));
+                }
+                step_level(levels, current_index, delta as i64)?;
+            }
+            FixtureProgramParameterType::Color { .. } => {
+                return Err(RuntimeError::TypeMismatch(
+                    "color parameter can not be incremented",
+                ))
             }
         }
+
+        Ok(self.current_value())
     }
 }
 
+/// Moves the current level of a discrete parameter by the given number of steps, wrapping around.
+/// Returns the name of the new level.
+fn step_level(
+    levels: &[FixtureProgramParameterDiscreteLevel],
+    current_index: &mut usize,
+    steps: i64,
+) -> std::result::Result<String, RuntimeError> {
+    // Setup rejects discrete parameters without levels, but better safe than sorry.
+    if levels.is_empty() {
+        return Err(RuntimeError::NoLevels);
+    }
+    let len = levels.len() as i64;
+    *current_index = (*current_index as i64 + steps.rem_euclid(len)).rem_euclid(len) as usize;
+    Ok(levels[*current_index].name.clone())
+}
+
 #[derive(Clone, Debug, Serialize)]
 struct FixtureProgramParameterDiscreteLevel {
     name: String,
     description: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_util::{self, FixturesDir};
+    use chrono::TimeZone;
+
+    const EMPTY_PROGRAM: &str = "function setup()\nend\n\nfunction tick(now)\nend\n";
+
+    /// Sets up a standalone Lua program from the given source.
+    fn lua_program(source: &str) -> Result<LuaFixtureProgram> {
+        lua_program_with_outputs(source, HashMap::new())
+    }
+
+    fn lua_program_with_outputs(
+        source: &str,
+        output_aliases: HashMap<String, Address>,
+    ) -> Result<LuaFixtureProgram> {
+        let dir = FixturesDir::new();
+        let path = dir.program("program.lua", source);
+        LuaFixtureProgram::new(
+            path,
+            output_aliases,
+            Local::now(),
+            0,
+            SelectedPrograms::default(),
+        )
+    }
+
+    /// Evaluates a Lua expression in the state of the given program.
+    fn eval<T: for<'lua> FromLua<'lua>>(
+        program: &LuaFixtureProgram,
+        expression: &str,
+    ) -> Result<T> {
+        Ok(program.lua.load(format!("return {}", expression)).eval()?)
+    }
+
+    const PARAMETERS_PROGRAM: &str = r#"function setup()
+    local p = new_discrete_parameter("mode")
+    add_discrete_parameter_level(p, "a", "A")
+    add_discrete_parameter_level(p, "b", "B")
+    declare_discrete_parameter(p)
+    declare_continuous_parameter("speed", 0.0, 1.0, 0.5)
+    declare_color_parameter("tint", 1.0, 0.5, 0.0)
+end
+
+function tick(now)
+end
+"#;
+
+    /// Writes the fixture `f` with the output `a` and the program `p` with a discrete parameter
+    /// `mode`, a continuous parameter `speed`, and a color parameter `tint`.
+    fn parameters_fixture() -> (FixturesDir, UniverseConfig) {
+        let dir = FixturesDir::new();
+        dir.fixture("f", r#"add_output_alias("a") add_program("p", "f/p.lua")"#);
+        dir.program("f/p.lua", PARAMETERS_PROGRAM);
+        (dir, test_util::universe_with_aliases(&["a"]))
+    }
+
+    fn state_json(fixture: &Fixture) -> serde_json::Value {
+        serde_json::to_value(fixture.export_state()).unwrap()
+    }
+
+    /// Sets up a program declaring a discrete parameter with three levels and the given default.
+    fn discrete_program(default_level: &str) -> Result<LuaFixtureProgram> {
+        lua_program(&format!(
+            r#"function setup()
+    local p = new_discrete_parameter("mode", nil, {})
+    add_discrete_parameter_level(p, "a", "A")
+    add_discrete_parameter_level(p, "b", "B")
+    add_discrete_parameter_level(p, "c", "C")
+    declare_discrete_parameter(p)
+end
+
+function tick(now)
+end
+"#,
+            default_level
+        ))
+    }
+
+    fn mode(program: &LuaFixtureProgram) -> ParameterValue {
+        program.parameters[0].value.current_value()
+    }
+
+    #[test]
+    fn discrete_default_level_by_name() {
+        let program = discrete_program(r#""c""#).unwrap();
+        assert_eq!(mode(&program), ParameterValue::Discrete("c".to_string()));
+    }
+
+    #[test]
+    fn discrete_default_level_by_index() {
+        let program = discrete_program("2").unwrap();
+        assert_eq!(mode(&program), ParameterValue::Discrete("c".to_string()));
+    }
+
+    #[test]
+    fn discrete_default_level_defaults_to_first() {
+        let program = discrete_program("nil").unwrap();
+        assert_eq!(mode(&program), ParameterValue::Discrete("a".to_string()));
+    }
+
+    #[test]
+    fn discrete_default_level_must_exist() {
+        assert!(discrete_program(r#""d""#).is_err());
+        assert!(discrete_program("3").is_err());
+        assert!(discrete_program("-1").is_err());
+    }
+
+    #[test]
+    fn export_round_trips_through_import() {
+        let (dir, universe) = parameters_fixture();
+        let mut fixture = dir.load("f", &universe);
+        let values = BTreeMap::from([
+            (
+                "mode".to_string(),
+                ParameterValue::Discrete("b".to_string()).into(),
+            ),
+            ("speed".to_string(), ParameterValue::Continuous(0.25).into()),
+        ]);
+        let results = fixture.get_program_mut("p").unwrap().set_parameters(values);
+        assert!(results.values().all(|res| res.is_ok()));
+        fixture.set_active_program("p").unwrap();
+        let exported = state_json(&fixture);
+
+        let mut imported = dir.load("f", &universe);
+        imported
+            .import_state(serde_json::from_value(exported.clone()).unwrap())
+            .unwrap();
+
+        assert_eq!(imported.active_program(), "p");
+        assert_eq!(state_json(&imported), exported);
+    }
+
+    #[test]
+    fn import_rejects_invalid_state_without_changes() {
+        let (dir, universe) = parameters_fixture();
+        let mut fixture = dir.load("f", &universe);
+        let before = state_json(&fixture);
+
+        let state = serde_json::json!({
+            "selected_program": "p",
+            "programs": {"p": {"mode": "b", "speed": 2.0}},
+        });
+        assert!(fixture
+            .import_state(serde_json::from_value(state).unwrap())
+            .is_err());
+
+        assert_eq!(state_json(&fixture), before);
+    }
+
+    #[test]
+    fn debug_globals_contains_scalar_globals() {
+        let program = lua_program(
+            r#"counter = 42
+label = "hello"
+items = {1, 2, 3}
+
+function setup()
+end
+
+function tick(now)
+end
+"#,
+        )
+        .unwrap();
+
+        let globals = program.debug_globals().unwrap();
+        assert_eq!(globals["counter"], serde_json::json!(42));
+        assert_eq!(globals["label"], serde_json::json!("hello"));
+        assert!(!globals.contains_key("items"));
+        assert!(!globals.contains_key("tick"));
+    }
+
+    #[test]
+    fn units_and_display_hints_are_part_of_metadata() {
+        let dir = FixturesDir::new();
+        dir.fixture("f", r#"add_output_alias("a") add_program("p", "f/p.lua")"#);
+        dir.program(
+            "f/p.lua",
+            r#"function setup()
+    local p = new_discrete_parameter("mode", "buttons")
+    add_discrete_parameter_level(p, "a", "A")
+    declare_discrete_parameter(p)
+    declare_continuous_parameter("speed", 0.0, 10.0, 1.0, "Hz", "slider")
+    declare_continuous_parameter("phase", 0.0, 1.0, 0.0)
+end
+
+function tick(now)
+end
+"#,
+        );
+        let fixture = dir.load("f", &test_util::universe_with_aliases(&["a"]));
+
+        let metadata = fixture.get_program("p").unwrap().metadata();
+        let parameters = serde_json::to_value(&metadata).unwrap()["parameters"].clone();
+        assert_eq!(parameters["mode"]["display"], "buttons");
+        assert_eq!(parameters["speed"]["unit"], "Hz");
+        assert_eq!(parameters["speed"]["display"], "slider");
+        assert!(parameters["phase"]["unit"].is_null());
+        assert!(parameters["phase"].get("display").is_none());
+    }
+
+    #[test]
+    fn channels_resolves_multi_channel_aliases() {
+        let output_aliases = (0..4)
+            .map(|i| (format!("px_{}", i), 10 + i))
+            .chain([("other".to_string(), 1)])
+            .collect();
+        let program = lua_program_with_outputs(EMPTY_PROGRAM, output_aliases).unwrap();
+
+        let addresses: Vec<Address> = eval(&program, r#"channels("px", 4)"#).unwrap();
+        assert_eq!(addresses, vec![10, 11, 12, 13]);
+        assert!(eval::<Vec<Address>>(&program, r#"channels("px", 5)"#).is_err());
+    }
+
+    #[test]
+    fn cycling_skips_manual_and_external_unless_all_are_included() {
+        let (dir, universe) = parameters_fixture();
+        let mut fixture = dir.load("f", &universe);
+        let cycle = |fixture: &mut Fixture, include_all| -> BTreeSet<String> {
+            (0..fixture.num_programs())
+                .map(|_| fixture.cycle_active_program(include_all).unwrap())
+                .collect()
+        };
+
+        let programs = cycle(&mut fixture, false);
+        assert!(programs.contains("p"));
+        assert!(!programs.contains("MANUAL"));
+        assert!(!programs.contains("EXTERNAL"));
+
+        let programs = cycle(&mut fixture, true);
+        assert_eq!(programs.len(), fixture.num_programs());
+        assert!(programs.contains("MANUAL"));
+        assert!(programs.contains("EXTERNAL"));
+    }
+
+    fn discrete(levels: &[&str]) -> FixtureProgramParameterType {
+        FixtureProgramParameterType::Discrete {
+            levels: levels
+                .iter()
+                .map(|name| FixtureProgramParameterDiscreteLevel {
+                    name: name.to_string(),
+                    description: name.to_uppercase(),
+                })
+                .collect(),
+            current_index: 0,
+        }
+    }
+
+    #[test]
+    fn cycling_discrete_parameter_without_levels_fails() {
+        let mut parameter = discrete(&[]);
+        assert!(matches!(
+            parameter.cycle(false),
+            Err(RuntimeError::NoLevels)
+        ));
+        assert!(matches!(parameter.cycle(true), Err(RuntimeError::NoLevels)));
+
+        // Setup is the primary guard.
+        let program = lua_program(
+            r#"function setup()
+    declare_discrete_parameter(new_discrete_parameter("mode"))
+end
+
+function tick(now)
+end
+"#,
+        );
+        assert!(program.is_err());
+    }
+
+    #[test]
+    fn cycling_discrete_parameter_wraps_around() {
+        let mut parameter = discrete(&["a", "b", "c"]);
+        assert_eq!(parameter.cycle(true).unwrap(), "c");
+        assert_eq!(parameter.cycle(false).unwrap(), "a");
+        assert_eq!(parameter.cycle(false).unwrap(), "b");
+    }
+
+    /// Replaces the values of all fields with the given key, at any depth.
+    fn replace_field(value: &mut serde_json::Value, key: &str, new: &serde_json::Value) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (k, v) in fields.iter_mut() {
+                    if k == key {
+                        *v = new.clone();
+                    } else {
+                        replace_field(v, key, new);
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => {
+                values.iter_mut().for_each(|v| replace_field(v, key, new))
+            }
+            _ => {}
+        }
+    }
+
+    /// Fetches the metadata of program `p`, modifies it, and posts it back.
+    fn post_modified_metadata(
+        fixture: &mut Fixture,
+        modify: impl FnOnce(&mut serde_json::Value),
+    ) -> Result<()> {
+        let metadata = fixture.get_program("p").unwrap().metadata();
+        let mut metadata = serde_json::to_value(&metadata).unwrap();
+        modify(&mut metadata["parameters"]);
+        fixture.set_program_parameters("p", serde_json::from_value(metadata).unwrap())
+    }
+
+    #[test]
+    fn parameters_can_be_set_from_modified_metadata() {
+        let (dir, universe) = parameters_fixture();
+        let mut fixture = dir.load("f", &universe);
+
+        post_modified_metadata(&mut fixture, |parameters| {
+            replace_field(&mut parameters["mode"], "current_level", &"b".into());
+            replace_field(&mut parameters["speed"], "current", &0.25.into());
+            replace_field(
+                &mut parameters["tint"],
+                "current",
+                &serde_json::json!([0.0, 0.0, 1.0]),
+            );
+        })
+        .unwrap();
+
+        let values = fixture.get_program("p").unwrap().parameter_values();
+        assert_eq!(values["mode"], ParameterValue::Discrete("b".to_string()));
+        assert_eq!(values["speed"], ParameterValue::Continuous(0.25));
+        assert_eq!(values["tint"], ParameterValue::Color([0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn invalid_metadata_changes_no_parameters() {
+        let (dir, universe) = parameters_fixture();
+        let mut fixture = dir.load("f", &universe);
+        let before = fixture.get_program("p").unwrap().parameter_values();
+
+        let res = post_modified_metadata(&mut fixture, |parameters| {
+            replace_field(&mut parameters["mode"], "current_level", &"b".into());
+            replace_field(&mut parameters["speed"], "current", &2.0.into());
+        });
+
+        assert!(res.is_err());
+        assert_eq!(fixture.get_program("p").unwrap().parameter_values(), before);
+    }
+
+    #[test]
+    fn failures_are_reported_as_runtime_errors() {
+        let (dir, universe) = parameters_fixture();
+        let mut fixture = dir.load("f", &universe);
+        assert!(matches!(
+            fixture.set_active_program("q"),
+            Err(RuntimeError::UnknownProgram(name)) if name == "q"
+        ));
+
+        let program = fixture.get_program_mut("p").unwrap();
+        let continuous = |value| ParameterSetRequest::Continuous { value }.into();
+        let discrete = |level: &str| {
+            ParameterSetRequest::Discrete {
+                level: level.to_string(),
+            }
+            .into()
+        };
+        let results = program.set_parameters(BTreeMap::from([
+            ("speed".to_string(), continuous(2.0)),
+            ("mode".to_string(), discrete("c")),
+            ("tint".to_string(), discrete("a")),
+            ("missing".to_string(), continuous(0.0)),
+        ]));
+        assert!(matches!(
+            results["speed"],
+            Err(RuntimeError::OutOfRange { value, lower, upper })
+                if value == 2.0 && lower == 0.0 && upper == 1.0
+        ));
+        assert!(matches!(&results["mode"], Err(RuntimeError::UnknownLevel(level)) if level == "c"));
+        assert!(matches!(
+            results["tint"],
+            Err(RuntimeError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            &results["missing"],
+            Err(RuntimeError::UnknownParameter { program, parameter })
+                if program == "p" && parameter == "missing"
+        ));
+
+        // Wrapped errors can be recovered from their context.
+        let err = lua_program(&format!(
+            "SOURCE_VERSION={}\n\n{}",
+            VERSION + 1,
+            EMPTY_PROGRAM
+        ))
+        .err()
+        .unwrap();
+        assert!(
+            matches!(
+                RuntimeError::find(&err),
+                Some(RuntimeError::VersionMismatch { expected, actual })
+                    if *expected == VERSION && *actual == VERSION + 1
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn slugs_resolve_to_parameters() {
+        let dir = FixturesDir::new();
+        dir.fixture("f", r#"add_program("Warm White", "f/p.lua")"#);
+        dir.program(
+            "f/p.lua",
+            r#"function setup()
+    declare_continuous_parameter("Color Temperature", 0.0, 1.0, 0.5)
+    declare_continuous_parameter("fade time", 0.0, 1.0, 0.5)
+    declare_continuous_parameter("Fade Time", 0.0, 1.0, 0.5)
+end
+
+function tick(now)
+end
+"#,
+        );
+        let fixture = dir.load("f", &test_util::universe(serde_json::json!([])));
+
+        let program = fixture.get_program("warm-white").unwrap();
+        assert_eq!(program.name, "Warm White");
+        assert_eq!(
+            program.get_parameter("color-temperature").unwrap().name,
+            "Color Temperature"
+        );
+
+        // Ambiguous slugs resolve to nothing, but the exact names still work.
+        assert!(program.get_parameter("fade-time").is_none());
+        assert_eq!(
+            program.get_parameter("Fade Time").unwrap().name,
+            "Fade Time"
+        );
+        assert_eq!(
+            program.get_parameter("fade time").unwrap().name,
+            "fade time"
+        );
+    }
+
+    #[test]
+    fn easing_functions_are_available() {
+        let program = lua_program(EMPTY_PROGRAM).unwrap();
+        let half_sqrt2 = std::f64::consts::FRAC_1_SQRT_2;
+        for (expression, expected) in [
+            ("ease_in(0.5)", 0.25),
+            ("ease_out(0.5)", 0.75),
+            ("ease_in_out(0.25)", 0.125),
+            ("ease_in_out(0.75)", 0.875),
+            ("ease_in_cubic(0.5)", 0.125),
+            ("ease_out_cubic(0.5)", 0.875),
+            ("ease_in_out_cubic(0.25)", 0.0625),
+            ("ease_in_out_cubic(0.75)", 0.9375),
+            ("ease_in_sine(0.5)", 1.0 - half_sqrt2),
+            ("ease_out_sine(0.5)", half_sqrt2),
+            ("ease_in_out_sine(0.5)", 0.5),
+            ("smoothstep(0, 10, 2.5)", 0.15625),
+            ("smootherstep(0, 10, 2.5)", 0.103515625),
+            // Inputs are clamped.
+            ("ease_in(-1)", 0.0),
+            ("ease_out_cubic(2)", 1.0),
+            ("smoothstep(0, 10, 20)", 1.0),
+            ("smootherstep(10, 10, 5)", 0.0),
+        ] {
+            let actual = eval::<f64>(&program, expression).unwrap();
+            assert!(
+                (actual - expected).abs() < 1e-9,
+                "{} = {}, expected {}",
+                expression,
+                actual,
+                expected
+            );
+        }
+    }
+
+    /// Draws the first few random numbers of the given program.
+    fn first_random_numbers(fixture: &Fixture, program: &str) -> Vec<f64> {
+        let FixtureProgramType::Lua(program) = &fixture.get_program(program).unwrap().inner else {
+            panic!("{} is not a Lua program", program)
+        };
+        let mut values: Vec<f64> = (0..3).map(|_| eval(program, "rand()").unwrap()).collect();
+        values.push(eval(program, "rand_range(2, 4)").unwrap());
+        values
+    }
+
+    #[test]
+    fn random_numbers_are_seeded_by_program_name() {
+        let dir = FixturesDir::new();
+        dir.fixture(
+            "f",
+            r#"add_program("p", "f/p.lua") add_program("q", "f/p.lua")"#,
+        );
+        dir.program("f/p.lua", EMPTY_PROGRAM);
+        let universe = test_util::universe(serde_json::json!([]));
+
+        let first = first_random_numbers(&dir.load("f", &universe), "p");
+        assert!(
+            first[..3].iter().all(|x| (0.0..1.0).contains(x)),
+            "{:?}",
+            first
+        );
+        assert!((2.0..4.0).contains(&first[3]), "{:?}", first);
+
+        // Reloading yields the same sequence, but other programs get their own.
+        let reloaded = dir.load("f", &universe);
+        assert_eq!(first_random_numbers(&reloaded, "p"), first);
+        assert_ne!(first_random_numbers(&reloaded, "q"), first);
+    }
+
+    #[test]
+    fn programs_can_branch_on_date() {
+        let program = lua_program(
+            r#"function setup()
+end
+
+function tick(now)
+end
+
+function weekend()
+    return DAY_OF_WEEK == 0 or DAY_OF_WEEK == 6
+end
+"#,
+        )
+        .unwrap();
+
+        let saturday = Local.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        LuaFixtureProgram::inject_environment(&program.lua, &saturday, None).unwrap();
+        assert!(eval::<bool>(&program, "weekend()").unwrap());
+        for (name, expected) in [
+            ("DAY_OF_WEEK", 6),
+            ("DAY_OF_MONTH", 17),
+            ("MONTH", 10),
+            ("DAY_OF_YEAR", 290),
+        ] {
+            assert_eq!(eval::<u32>(&program, name).unwrap(), expected, "{}", name);
+        }
+
+        let monday = Local.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+        LuaFixtureProgram::inject_environment(&program.lua, &monday, None).unwrap();
+        assert!(!eval::<bool>(&program, "weekend()").unwrap());
+    }
+
+    #[test]
+    fn slugs_address_programs_when_changing_state() {
+        let dir = FixturesDir::new();
+        dir.fixture("f", r#"add_program("Warm White", "f/p.lua")"#);
+        dir.program("f/p.lua", PARAMETERS_PROGRAM);
+        let universe = test_util::universe(serde_json::json!([]));
+        let mut fixture = dir.load("f", &universe);
+
+        fixture.set_active_program("warm-white").unwrap();
+        assert_eq!(fixture.active_program(), "Warm White");
+
+        let mut metadata =
+            serde_json::to_value(fixture.get_program("warm-white").unwrap().metadata()).unwrap();
+        replace_field(
+            &mut metadata["parameters"]["speed"],
+            "current",
+            &0.25.into(),
+        );
+        fixture
+            .set_program_parameters("warm-white", serde_json::from_value(metadata).unwrap())
+            .unwrap();
+        let values = fixture
+            .get_program("Warm White")
+            .unwrap()
+            .parameter_values();
+        assert_eq!(values["speed"], ParameterValue::Continuous(0.25));
+
+        let mut imported = dir.load("f", &universe);
+        imported
+            .import_state(
+                serde_json::from_value(serde_json::json!({
+                    "selected_program": "warm-white",
+                    "programs": {"warm-white": {"speed": 0.75}},
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(imported.active_program(), "Warm White");
+        let values = imported
+            .get_program("Warm White")
+            .unwrap()
+            .parameter_values();
+        assert_eq!(values["speed"], ParameterValue::Continuous(0.75));
+    }
+
+    #[test]
+    fn dangerous_libraries_are_unavailable() {
+        let program = lua_program(EMPTY_PROGRAM).unwrap();
+
+        for name in [
+            "os", "io", "debug", "package", "require", "dofile", "loadfile",
+        ] {
+            assert!(
+                eval::<bool>(&program, &format!("{} == nil", name)).unwrap(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn memory_is_limited() {
+        let program = lua_program(
+            r#"function setup()
+    local t = {}
+    for i = 1, 100000000 do
+        t[i] = string.rep("x", 1024) .. i
+    end
+end
+
+function tick(now)
+end
+"#,
+        );
+
+        let err = program.err().unwrap();
+        assert!(
+            err.chain().any(|e| matches!(
+                e.downcast_ref::<mlua::Error>(),
+                Some(mlua::Error::MemoryError(_))
+            )),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -2,7 +2,8 @@
 -- These are provided by the runtime during setup
 
 -- Set a name for the fixture.
--- This must be unique in the Kaleidoscope instance.
+-- This must be unique in the Kaleidoscope instance, and consist of ASCII letters, digits, - and _ only, since it is
+-- used in the URLs of the HTTP API.
 function fixture_name(name) end
 
 -- Add an alias to the list of outputs of this Fixture.
@@ -18,4 +19,36 @@ function add_program(program_name, program_source_path) end
 function disable_builtin_programs(b) end
 
 -- Control whether the builtin program MANUAL for manual output control should be disabled.
-function disable_manual_program(b) end
\ No newline at end of file
+function disable_manual_program(b) end
+
+-- Set the priority of this fixture, between 0 and 20, defaulting to 0.
+-- Fixtures are ticked in ascending order of priority, i.e., outputs produced by fixtures with higher priority are
+-- sent later.
+function set_priority(p) end
+
+-- Add tags to this Fixture, for grouping fixtures, e.g., fixture_tags({"wash", "left"}).
+-- Tags can be used to filter fixtures via the HTTP API.
+function fixture_tags(tags) end
+
+-- Set the position of an output in space, by alias, for previews.
+-- The z coordinate is optional and defaults to 0.
+-- The output must be added to this Fixture as well.
+function set_position(alias, x, y, z) end
+
+-- Declare an output, by alias, as binary, i.e., only ever on or off, e.g., a relay.
+-- Binary outputs are not dimmed by the master brightness.
+-- The output must be added to this Fixture as well.
+function set_output_binary(alias) end
+
+-- Set the range of the parameter for an output, by alias, of the builtin MANUAL program.
+-- The range is mapped to the full range of the output. Defaults to [0, 1].
+function set_manual_range(alias, lower, upper) end
+
+-- Declare three outputs, by alias, as the red, green, and blue channels of a color for the builtin MANUAL program.
+-- The MANUAL program then has an additional discrete parameter "preset", which sets all three channels to a named color.
+-- Changing one of the channels individually resets the preset to "custom".
+function set_manual_color_channels(r_alias, g_alias, b_alias) end
+
+-- Add a named color preset for the MANUAL program, with components in [0, 1].
+-- This requires color channels to be declared. If none are added, a default set of colors is used.
+function add_manual_color_preset(name, r, g, b) end
@@ -0,0 +1,36 @@
+SOURCE_VERSION=$VERSION
+
+function setup()
+    -- Set a name for the Fixture.
+    -- This must be unique in the Kaleidoscope instance.
+    fixture_name("$NAME")
+
+    -- Add outputs, by alias or by address.
+    --add_output_alias('$NAME-r')
+    --add_output_address(123)
+
+    -- Positions of outputs, as x, y and optionally z, for previews.
+    --set_position('$NAME-r', 0.0, 0.0)
+
+    -- Outputs which are only ever on or off, which are not dimmed by the master brightness.
+    --set_output_binary('$NAME-relay')
+
+    -- Tags for grouping fixtures, e.g., to filter them via the HTTP API.
+    --fixture_tags({"wash", "left"})
+
+    -- Whether to disable the builtin MANUAL program.
+    --disable_manual_program(true)
+
+    -- Parameter ranges for the builtin MANUAL program, defaulting to [0, 1].
+    --set_manual_range('$NAME-r', 0, 255)
+
+    -- Color channels and presets for the builtin MANUAL program.
+    --set_manual_color_channels('$NAME-r', '$NAME-g', '$NAME-b')
+    --add_manual_color_preset('warm', 1.0, 0.6, 0.2)
+
+    -- Whether to disable the builtin ON and OFF programs.
+    --disable_builtin_programs(true)
+
+    -- Programs to load, relative to this file.
+    add_program("default", "$NAME/default.lua")
+end
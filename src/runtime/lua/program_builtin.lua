@@ -9,6 +9,19 @@ START = 123.45
 -- This example value is 14:36:12.
 TIME_OF_DAY = 14*60*60 + 36*60 + 12
 
+-- Contain the local date, set by the runtime together with TIME_OF_DAY.
+-- DAY_OF_WEEK is in [0,6], starting with Sunday, DAY_OF_MONTH in [1,31], MONTH in [1,12], and DAY_OF_YEAR in [1,366].
+-- These example values are for Tuesday, the 3rd of March.
+DAY_OF_WEEK = 2
+DAY_OF_MONTH = 3
+MONTH = 3
+DAY_OF_YEAR = 62
+
+-- Contain the local times of sunrise and sunset today, in seconds since midnight, set by the runtime.
+-- These are nil if no location is configured, during polar day or night, and during setup.
+SUNRISE = 6*60*60 + 45*60
+SUNSET = 17*60*60 + 56*60
+
 -- clamp clamps x to [from, to]
 function clamp(from, to, x)
     if x < from then
@@ -61,19 +74,73 @@ function noise2d(x, y) return 0.0 end
 function noise3d(x, y, z) return 0.0 end
 function noise3d(x, y, z, t) return 0.0 end
 
+-- Easing functions, mapping t in [0,1] to [0,1].
+-- Inputs outside of [0,1] are clamped.
+-- These are implemented in Rust.
+function ease_in(t) return t end
+function ease_out(t) return t end
+function ease_in_out(t) return t end
+function ease_in_cubic(t) return t end
+function ease_out_cubic(t) return t end
+function ease_in_out_cubic(t) return t end
+function ease_in_sine(t) return t end
+function ease_out_sine(t) return t end
+function ease_in_out_sine(t) return t end
+
+-- smoothstep and smootherstep interpolate smoothly from 0 to 1 as x goes from a to b, clamped outside of that.
+-- These are implemented in Rust.
+function smoothstep(a, b, x) return 0.0 end
+function smootherstep(a, b, x) return 0.0 end
+
+-- hsv_to_rgb converts a color from HSV to RGB, returning r, g, and b in [0,1].
+-- The hue wraps around, i.e., 1.25 is the same as 0.25, saturation and value are clamped to [0,1].
+-- This is implemented in Rust.
+function hsv_to_rgb(h, s, v) return v, v, v end
+
+-- rgb_to_hsv converts a color from RGB to HSV, returning h, s, and v in [0,1].
+-- The hue of grays is 0.
+-- This is implemented in Rust.
+function rgb_to_hsv(r, g, b) return 0.0, 0.0, math.max(r, g, b) end
+
+-- rand returns a random number in [0,1).
+-- Each program has its own RNG, seeded from the names of its fixture and itself, which produces the same sequence
+-- every time the program is loaded. Use this instead of math.random for reproducible shows.
+-- This is implemented in Rust.
+function rand() return 0.5 end
+
+-- rand_range returns a random number in [lo,hi), using rand.
+function rand_range(lo, hi)
+    return lo + (hi - lo) * rand()
+end
+
 
 -- =============================================
 -- Setup-related things
 
+-- Enables or disables slow mode.
+-- Pass true to run the program every 1000 ticks, or a number of ticks to run it at that interval instead.
 function set_slow_mode(to) end
 
+-- Enables or disables clamping of continuous parameters.
+-- Pass true to clamp values set out of range to the nearest limit, instead of rejecting them, e.g., for MIDI
+-- controllers which overshoot slightly.
+function set_clamp_parameters(to) end
+
 PARAMETER_TYPE_DISCRETE = 'discrete'
 PARAMETER_TYPE_CONTINUOUS = 'continuous'
+PARAMETER_TYPE_INTEGER = 'integer'
+PARAMETER_TYPE_COLOR = 'color'
 
-function new_discrete_parameter(name)
+-- Creates a new discrete parameter.
+-- Optionally, a display hint for UIs and the default level can be provided.
+-- The default level is given by its name, or by its index, counting levels in the order they are added from 0.
+-- The default level defaults to the first level added.
+function new_discrete_parameter(name, display, default_level)
     local p={}
     p['_type'] = PARAMETER_TYPE_DISCRETE
     p['_name'] = name
+    p['_display'] = display
+    p['_default'] = default_level
     p['_i'] = 0
     p['_levels'] = {}
     return p
@@ -93,13 +160,51 @@ function declare_discrete_parameter(p)
     _declare_parameter_generic(p)
 end
 
-function declare_continuous_parameter(name, lower_limit_incl, upper_limit_incl, default_value)
+-- Declares a continuous parameter.
+-- Optionally, a unit (e.g., "Hz", "seconds"), a display hint for UIs (e.g., "percent"), and a step for UIs to use
+-- for sliders (e.g., 0.1) can be provided.
+function declare_continuous_parameter(name, lower_limit_incl, upper_limit_incl, default_value, unit, display, step)
     local p={}
     p['_type'] = PARAMETER_TYPE_CONTINUOUS
     p['_name'] = name
     p['_lower'] = lower_limit_incl
     p['_upper'] = upper_limit_incl
     p['_default'] = default_value
+    p['_unit'] = unit
+    p['_display'] = display
+    p['_step'] = step
+
+    _declare_parameter_generic(p)
+end
+
+-- Declares an integer parameter, e.g., a number of steps.
+-- Cycling an integer parameter steps it by one, wrapping around to the lower limit.
+-- Optionally, a unit and a display hint for UIs can be provided.
+function declare_integer_parameter(name, lower_limit_incl, upper_limit_incl, default_value, unit, display)
+    local p={}
+    p['_type'] = PARAMETER_TYPE_INTEGER
+    p['_name'] = name
+    p['_lower'] = lower_limit_incl
+    p['_upper'] = upper_limit_incl
+    p['_default'] = default_value
+    p['_unit'] = unit
+    p['_display'] = display
+
+    _declare_parameter_generic(p)
+end
+
+-- Declares a color parameter, with default red, green, and blue components in [0, 1].
+-- The value of a color parameter is a table {r, g, b}.
+-- Color parameters can not be cycled.
+-- Optionally, a display hint for UIs can be provided.
+function declare_color_parameter(name, r, g, b, display)
+    local p={}
+    p['_type'] = PARAMETER_TYPE_COLOR
+    p['_name'] = name
+    p['_r'] = r
+    p['_g'] = g
+    p['_b'] = b
+    p['_display'] = display
 
     _declare_parameter_generic(p)
 end
@@ -128,6 +233,16 @@ function output_alias_to_address(alias)
     return addr
 end
 
+-- Returns the name of the currently selected program of the named fixture, e.g., to check whether it is EXTERNAL.
+-- Raises an error if the fixture is unknown.
+-- This reflects the state at the beginning of the current tick and is not available during setup.
+-- Implemented in Rust.
+function get_fixture_program(fixture_name) return "OFF" end
+
+-- Resolves the output aliases alias_0 to alias_{count-1} to a table of their addresses, for multi-channel fixtures.
+-- Raises an error if any of them is unknown. Implemented in Rust.
+function channels(alias, count) return {} end
+
 -- This is returned to the runtime after each tick.
 _output_values_by_address = {}
 
@@ -137,14 +252,24 @@ function set_alias(alias, value)
     _output_values_by_address[output_alias_to_address(alias)] = value
 end
 
+-- Programs can optionally define these lifecycle hooks, which are called by the runtime:
+-- on_enable() is called when the program is switched to, before its first tick.
+-- on_disable() is called when the fixture switches away from the program.
+
 -- This is called by the runtime.
 -- By calling tick() from within Lua and returning the table in just
 -- one function call we avoid one trip through the C FFI.
+-- tick() may also return a table of values, keyed by address or alias, which is passed on as is
+-- and takes precedence over values set via set_alias.
+-- Anything else tick() returns is ignored.
 function _tick(now)
     -- This clears the previous tick's map.
     -- It costs performance, but otherwise we cannot distinguish whether a program wrote a value
     -- during this tick or some previous tick.
     _output_values_by_address = {}
-    tick(now)
-    return _output_values_by_address
+    local returned = tick(now)
+    if type(returned) ~= "table" then
+        returned = nil
+    end
+    return _output_values_by_address, returned
 end
\ No newline at end of file
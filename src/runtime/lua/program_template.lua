@@ -0,0 +1,14 @@
+SOURCE_VERSION=$VERSION
+
+function setup()
+    -- Declare parameters, which can be changed through the HTTP API.
+    --declare_continuous_parameter("speed", 0.0, 1.0, 0.5)
+
+    -- Run only every few ticks.
+    --set_slow_mode(true)
+end
+
+function tick(now)
+    -- Compute outputs based on now, and set them.
+    --set_alias('$NAME-r', map_to_value(-1, 1, math.sin(now)))
+end
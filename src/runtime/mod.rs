@@ -1,2 +1,12 @@
+pub(crate) mod audit;
+pub(crate) mod bundle;
+pub(crate) mod color;
+pub(crate) mod easing;
+pub(crate) mod error;
+pub(crate) mod events;
 pub(crate) mod fixture;
 pub(crate) mod runtime;
+pub(crate) mod sun;
+#[cfg(test)]
+pub(crate) mod test_util;
+pub(crate) mod warnings;
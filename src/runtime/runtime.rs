@@ -1,125 +1,906 @@
-use crate::runtime::fixture::Fixture;
-use alloy::api::SetRequest;
+use crate::config::Location;
+use crate::prom;
+use crate::runtime::bundle::ExtractedBundle;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::events::{StateEvent, EVENT_CAPACITY};
+use crate::runtime::fixture::{
+    Fixture, FixtureSetup, FixtureState, ParameterRequest, Position, SelectedPrograms,
+};
+use crate::runtime::sun::SunTimes;
+use crate::runtime::warnings::WarningLimiter;
+use crate::runtime::{bundle, fixture, sun};
+use alloy::api::{SetRequest, SetRequestTarget};
 use alloy::config::UniverseConfig;
-use anyhow::{bail, Context, Result};
-use chrono::{DateTime, Local};
-use log::{debug, warn};
+use alloy::{Address, OutputValue, HIGH, LOW};
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use log::{debug, error, info, warn};
+use prometheus::IntCounter;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::Path;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+use tokio::task;
 
 pub(crate) struct TickState {
     pub(crate) timestamp: Instant,
     pub(crate) local_time: DateTime<Local>,
+    /// Times of sunrise and sunset today, if a location is configured and the sun rises.
+    pub(crate) sun_times: Option<SunTimes>,
+}
+
+/// Metadata about all fixtures, extending alloy's metadata with runtime state.
+#[derive(Serialize)]
+pub(crate) struct KaleidoscopeMetadata {
+    fixtures: HashMap<String, FixtureMetadata>,
+    paused: bool,
+    /// The fixture which is the only one producing outputs, if any.
+    solo: Option<String>,
+}
+
+/// Metadata about a fixture, extending alloy's metadata with runtime state.
+#[derive(Serialize)]
+pub(crate) struct FixtureMetadata {
+    #[serde(flatten)]
+    inner: fixture::FixtureMetadata,
+    tick_errors: TickErrorCounts,
+    last_error: Option<TickError>,
+    disabled: bool,
+}
+
+/// The most recent error produced while ticking a fixture.
+/// This is kept after the fixture recovers, see [TickErrorCounts::consecutive] to check whether
+/// the fixture is currently failing.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TickError {
+    message: String,
+    timestamp: DateTime<Local>,
+}
+
+/// Counts of failed ticks of a fixture.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub(crate) struct TickErrorCounts {
+    total: u64,
+    consecutive: u64,
+}
+
+/// The most recent value of every fixture output, for driving visualizers without hardware.
+#[derive(Serialize)]
+pub(crate) struct PreviewFeed {
+    outputs: Vec<PreviewOutput>,
+}
+
+#[derive(Serialize)]
+struct PreviewOutput {
+    fixture: String,
+    alias: String,
+    address: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<Position>,
+    /// The last value produced for this output, if any.
+    value: Option<OutputValue>,
+}
+
+/// Options for the runtime and its fixtures.
+#[derive(Clone, Debug)]
+pub(crate) struct RuntimeOptions {
+    /// Interval within which identical warnings are logged only once.
+    pub(crate) warning_interval: Duration,
+    /// Duration over which outputs of newly loaded fixtures are ramped up.
+    pub(crate) startup_fade: Duration,
+    /// Default duration over which outputs crossfade when a fixture switches programs.
+    pub(crate) program_fade: Duration,
+    /// Whether to drop the set requests of a fixture if they are identical to those of its
+    /// previous tick.
+    pub(crate) suppress_unchanged: bool,
+    /// Whether to only send set requests for addresses whose value changed since it was last
+    /// sent.
+    pub(crate) send_changed_only: bool,
+    /// Whether to tick fixtures in parallel.
+    pub(crate) parallel_ticks: bool,
+    /// File to save the selected programs and parameter values of all fixtures to, and to
+    /// restore them from on startup.
+    pub(crate) state_file: Option<PathBuf>,
+    /// Whether multiple fixtures may drive the same output address.
+    pub(crate) allow_output_overlap: bool,
+    /// Whether a program writing outputs its fixture didn't declare fails the tick, instead of
+    /// these outputs being dropped.
+    pub(crate) strict_outputs: bool,
+    /// Overrides of output aliases, from the alias of the universe to the local alias.
+    pub(crate) alias_overrides: HashMap<String, String>,
+    /// Location used to calculate sunrise and sunset, if any.
+    pub(crate) location: Option<Location>,
 }
 
 struct WrappedFixture {
     inner: Fixture,
     set_requests: Vec<SetRequest>,
+    tick_errors: TickErrorCounts,
+    last_error: Option<TickError>,
+    /// Whether the fixture is skipped during ticks, i.e., its outputs stay at their last values.
+    disabled: bool,
+    fade_in: Option<FadeIn>,
+    /// The program which was active during the previous tick, to detect program switches.
+    previous_program: String,
+    /// Whether the active program changed with the current tick.
+    switched_program: bool,
+    /// Duration of the crossfade for the next program switch, if not the default.
+    next_program_fade: Option<Duration>,
+    program_fade: Duration,
+    crossfade: Option<Crossfade>,
+    strict_outputs: bool,
+    /// Addresses the program wrote during the current tick which are not outputs of the fixture.
+    undeclared_outputs: BTreeSet<Address>,
+    /// The set requests of the previous tick, if unchanged outputs are suppressed.
+    previous_set_requests: Option<Vec<SetRequest>>,
+    outputs: FixtureOutputs,
+    /// Per-fixture metrics, looked up once to avoid doing so on every tick.
+    /// This also exports them as zero before the first tick.
+    tick_counter: IntCounter,
+    tick_error_counter: IntCounter,
+}
+
+/// The most recent value produced for each output of a fixture.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct FixtureOutputs {
+    /// When the last values were produced, if any.
+    timestamp: Option<DateTime<Local>>,
+    values: BTreeMap<Address, OutputValue>,
+}
+
+/// Ramps the outputs of a freshly loaded fixture from LOW to their targets, to avoid inrush
+/// currents from switching many outputs on at once.
+struct FadeIn {
+    duration: Duration,
+    start: Option<Instant>,
+    /// The most recent value produced by the program for each address.
+    targets: BTreeMap<Address, OutputValue>,
+}
+
+impl FadeIn {
+    /// Replaces the set requests for addresses with the scaled targets.
+    /// Since programs don't necessarily produce values on every tick, all known targets are
+    /// re-sent on every tick of the fade.
+    /// Returns whether the fade is complete.
+    fn apply(&mut self, now: Instant, set_requests: &mut Vec<SetRequest>) -> bool {
+        let start = *self.start.get_or_insert(now);
+        set_requests.retain(|req| match &req.target {
+            SetRequestTarget::Address(addr) => {
+                self.targets.insert(*addr, req.value);
+                false
+            }
+            _ => true,
+        });
+
+        let progress =
+            (now.duration_since(start).as_secs_f64() / self.duration.as_secs_f64()).clamp(0.0, 1.0);
+        set_requests.extend(self.targets.iter().map(|(addr, target)| SetRequest {
+            value: (*target as f64 * progress).round() as OutputValue,
+            target: SetRequestTarget::Address(*addr),
+        }));
+
+        progress >= 1.0
+    }
+}
+
+/// Interpolates the outputs of a fixture from the last values of its previous program to those of
+/// the newly selected program.
+struct Crossfade {
+    duration: Duration,
+    start: Instant,
+    /// The last value produced by the previous program for each address.
+    from: BTreeMap<Address, OutputValue>,
+    /// The most recent value produced by the new program for each address.
+    targets: BTreeMap<Address, OutputValue>,
+}
+
+impl Crossfade {
+    /// Replaces the set requests for addresses with the interpolated values.
+    /// Addresses for which the new program didn't produce a value yet keep their previous value.
+    /// As with [FadeIn], all known values are re-sent on every tick of the fade.
+    /// Returns whether the fade is complete.
+    fn apply(&mut self, now: Instant, set_requests: &mut Vec<SetRequest>) -> bool {
+        set_requests.retain(|req| match &req.target {
+            SetRequestTarget::Address(addr) => {
+                self.targets.insert(*addr, req.value);
+                false
+            }
+            _ => true,
+        });
+
+        let progress = (now.duration_since(self.start).as_secs_f64() / self.duration.as_secs_f64())
+            .clamp(0.0, 1.0);
+        let addresses: BTreeSet<_> = self.from.keys().chain(self.targets.keys()).collect();
+        set_requests.extend(addresses.into_iter().map(|addr| {
+            let target = self.targets.get(addr).or_else(|| self.from.get(addr));
+            let from = self.from.get(addr).or(target);
+            let (from, target) = (*from.unwrap() as f64, *target.unwrap() as f64);
+            SetRequest {
+                value: (from + (target - from) * progress)
+                    .round()
+                    .clamp(LOW as f64, HIGH as f64) as OutputValue,
+                target: SetRequestTarget::Address(*addr),
+            }
+        }));
+
+        progress >= 1.0
+    }
 }
 
 impl WrappedFixture {
-    fn wrap(fixture: Fixture) -> WrappedFixture {
+    fn wrap(
+        mut fixture: Fixture,
+        options: &RuntimeOptions,
+        events: &broadcast::Sender<StateEvent>,
+    ) -> WrappedFixture {
+        fixture.events = Some(events.clone());
         let num_outputs = fixture.addresses.len();
+        let tick_counter = prom::FIXTURE_TICKS.with_label_values(&[&fixture.name]);
+        let tick_error_counter = prom::FIXTURE_TICK_ERRORS.with_label_values(&[&fixture.name]);
         WrappedFixture {
+            previous_program: fixture.active_program().to_string(),
+            switched_program: false,
             inner: fixture,
             set_requests: Vec::with_capacity(num_outputs),
+            tick_errors: Default::default(),
+            last_error: None,
+            disabled: false,
+            fade_in: if options.startup_fade.is_zero() {
+                None
+            } else {
+                Some(FadeIn {
+                    duration: options.startup_fade,
+                    start: None,
+                    targets: BTreeMap::new(),
+                })
+            },
+            next_program_fade: None,
+            program_fade: options.program_fade,
+            crossfade: None,
+            strict_outputs: options.strict_outputs,
+            undeclared_outputs: BTreeSet::new(),
+            previous_set_requests: if options.suppress_unchanged {
+                Some(Vec::with_capacity(num_outputs))
+            } else {
+                None
+            },
+            outputs: Default::default(),
+            tick_counter,
+            tick_error_counter,
         }
     }
 
-    fn tick(&mut self, state: &TickState) -> Result<&[SetRequest]> {
+    fn metadata(&self, universe: &UniverseConfig) -> FixtureMetadata {
+        FixtureMetadata {
+            inner: self.inner.metadata(universe),
+            tick_errors: self.tick_errors,
+            last_error: self.last_error.clone(),
+            disabled: self.disabled,
+        }
+    }
+
+    /// Starts a crossfade if the active program changed since the previous tick.
+    /// Switching to EXTERNAL doesn't fade, since Kaleidoscope no longer controls the outputs.
+    fn check_program_switch(&mut self, now: Instant) {
+        self.switched_program = self.inner.active_program() != self.previous_program;
+        if !self.switched_program {
+            // The override only applies to a switch made together with it.
+            self.next_program_fade = None;
+            return;
+        }
+        self.previous_program = self.inner.active_program().to_string();
+
+        let duration = self.next_program_fade.take().unwrap_or(self.program_fade);
+        if duration.is_zero()
+            || self.fade_in.is_some()
+            || self.previous_program == "EXTERNAL"
+            || self.outputs.values.is_empty()
+        {
+            self.crossfade = None;
+            return;
+        }
+        debug!(
+            "{}: crossfading to {} over {:?}",
+            self.inner.name, self.previous_program, duration
+        );
+        self.crossfade = Some(Crossfade {
+            duration,
+            start: now,
+            from: self.outputs.values.clone(),
+            targets: BTreeMap::new(),
+        });
+    }
+
+    /// Runs the current program of the fixture.
+    /// Afterwards, `set_requests` holds the set requests to send for this tick.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(fixture = %self.inner.name))
+    )]
+    fn tick(&mut self, state: &TickState) -> Result<()> {
         self.set_requests.clear();
-        self.inner
-            .run_current_program(state, &mut self.set_requests)?;
+        if self.disabled {
+            return Ok(());
+        }
+        self.check_program_switch(state.timestamp);
+        let res = self
+            .inner
+            .run_current_program(state, &mut self.set_requests)
+            .and_then(|()| {
+                self.undeclared_outputs =
+                    self.inner.retain_declared_outputs(&mut self.set_requests);
+                ensure!(
+                    !self.strict_outputs || self.undeclared_outputs.is_empty(),
+                    "program {} wrote undeclared outputs {:?}",
+                    self.inner.active_program(),
+                    self.undeclared_outputs
+                );
+                Ok(())
+            });
+        if let Err(err) = res {
+            // Don't send partial outputs.
+            self.set_requests.clear();
+            self.tick_errors.total += 1;
+            self.tick_errors.consecutive += 1;
+            self.last_error = Some(TickError {
+                message: format!("{:#}", err),
+                timestamp: state.local_time,
+            });
+            return Err(err);
+        }
+        self.tick_errors.consecutive = 0;
+
+        if let Some(fade_in) = &mut self.fade_in {
+            if fade_in.apply(state.timestamp, &mut self.set_requests) {
+                debug!("{}: startup fade-in complete", self.inner.name);
+                self.fade_in = None;
+            }
+        }
+
+        if let Some(crossfade) = &mut self.crossfade {
+            if crossfade.apply(state.timestamp, &mut self.set_requests) {
+                debug!("{}: program crossfade complete", self.inner.name);
+                self.crossfade = None;
+            }
+        }
 
         debug!(
             "{}::run_current_program produced set requests {:?}",
             self.inner.name, self.set_requests
         );
 
-        Ok(&self.set_requests)
+        // Programs don't necessarily produce values for all outputs on every tick.
+        if !self.set_requests.is_empty() {
+            self.outputs.timestamp = Some(state.local_time);
+            for req in self.set_requests.iter() {
+                if let SetRequestTarget::Address(addr) = req.target {
+                    self.outputs.values.insert(addr, req.value);
+                }
+            }
+        }
+
+        if let Some(previous) = &mut self.previous_set_requests {
+            // Lua programs produce their outputs in no particular order.
+            self.set_requests.sort_by_key(sort_key);
+            if same_set_requests(previous, &self.set_requests) {
+                self.set_requests.clear();
+                return Ok(());
+            }
+            previous.clone_from(&self.set_requests);
+        }
+
+        Ok(())
     }
 }
 
+/// Checks whether two lists of set requests set the same addresses to the same values, in the
+/// same order.
+/// Requests not targeting an address are never considered the same.
+fn same_set_requests(a: &[SetRequest], b: &[SetRequest]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b.iter()).all(|(a, b)| {
+            a.value == b.value
+                && match (&a.target, &b.target) {
+                    (SetRequestTarget::Address(a), SetRequestTarget::Address(b)) => a == b,
+                    _ => false,
+                }
+        })
+}
+
 pub(crate) struct Runtime {
     fixtures: Vec<WrappedFixture>,
     set_requests: Vec<SetRequest>,
+    /// Set requests not produced by any fixture, which are sent with the next tick.
+    queued_set_requests: Vec<SetRequest>,
+    paused: bool,
+    warnings: WarningLimiter,
+    options: RuntimeOptions,
+    /// The extracted fixtures bundles, if fixtures were loaded from any.
+    /// Fixtures are reloaded from here.
+    bundles: Vec<ExtractedBundle>,
+    /// Serialized metadata about all fixtures, invalidated whenever fixtures change.
+    metadata_cache: Option<Arc<String>>,
+    /// Whether fixtures possibly changed since their state was last saved.
+    state_dirty: bool,
+    /// Times of sunrise and sunset, cached for the date they were calculated for.
+    sun_times: Option<(NaiveDate, Option<SunTimes>)>,
+    /// The last value sent for each address, if only changed values are sent.
+    sent_values: Option<HashMap<Address, OutputValue>>,
+    /// Whether to send the last value of every address with the next tick, regardless of
+    /// whether it changed.
+    resend_all: bool,
+    /// Factor in `[0, 1]` by which the outputs of all fixtures are scaled, except for binary
+    /// outputs.
+    master_brightness: f64,
+    /// The fixture which is the only one producing outputs, if any.
+    /// The outputs of all other fixtures are held at LOW.
+    solo: Option<String>,
+    /// Publishes program switches and parameter changes of all fixtures.
+    events: broadcast::Sender<StateEvent>,
+    /// The selected program of every fixture, as seen by Lua programs.
+    selected_programs: SelectedPrograms,
 }
 
 impl Runtime {
     pub(crate) fn new<P: AsRef<Path>>(
-        fixtures_root: P,
+        fixtures_roots: &[P],
         universe_config: &UniverseConfig,
+        options: RuntimeOptions,
     ) -> Result<Runtime> {
-        let mut fixtures: Vec<Fixture> = Vec::new();
-        for entry in fs::read_dir(&fixtures_root).context("unable to list fixtures")? {
-            let entry = entry.context("unable to enumerate fixtures sources")?;
-            let path = entry.path();
-            if path.is_dir() {
-                // Skip
-                continue;
-            }
+        let selected_programs = SelectedPrograms::default();
+        let LoadedFixtures {
+            bundles,
+            mut fixtures,
+            failed,
+        } = load_fixtures(
+            fixtures_roots,
+            universe_config,
+            &options.alias_overrides,
+            &selected_programs,
+        )?;
+        if let Some((path, err)) = failed.into_iter().next() {
+            return Err(err.context(format!("unable to load fixture at {:?}", &path)));
+        }
 
-            // Attempt to load as a fixture
-            let fix = Fixture::new(&path, universe_config)
-                .context(format!("unable to load fixture at {:?}", &path))?;
+        check_duplicate_names(&fixtures)?;
+        for fix in fixtures.iter() {
+            fix.export_metrics();
+        }
 
-            if let Some(f) = fixtures.iter().find(|f| f.name == fix.name) {
-                bail!(
-                    "duplicate fixture: {} in file {:?} (other was {:?})",
-                    fix.name,
-                    &path,
-                    &f.source_path
-                )
-            }
+        // Tick fixtures in order of ascending priority.
+        fixtures.sort_by_key(|f| f.priority);
 
-            fixtures.push(fix)
+        check_output_overlap(&fixtures, options.allow_output_overlap)?;
+
+        if let Some(state_file) = &options.state_file {
+            restore_state(state_file, &mut fixtures);
         }
 
-        Ok(Runtime {
-            fixtures: fixtures.into_iter().map(WrappedFixture::wrap).collect(),
+        let sent_values = if options.send_changed_only {
+            Some(HashMap::new())
+        } else {
+            None
+        };
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let runtime = Runtime {
+            fixtures: fixtures
+                .into_iter()
+                .map(|f| WrappedFixture::wrap(f, &options, &events))
+                .collect(),
             set_requests: Vec::with_capacity(16),
-        })
+            queued_set_requests: Vec::new(),
+            paused: false,
+            warnings: WarningLimiter::new(options.warning_interval),
+            options,
+            bundles,
+            metadata_cache: None,
+            state_dirty: false,
+            sun_times: None,
+            sent_values,
+            resend_all: false,
+            master_brightness: 1.0,
+            solo: None,
+            events,
+            selected_programs,
+        };
+        runtime.update_program_metrics();
+        // Programs may read this before the first tick, e.g., when enabled.
+        runtime.update_selected_programs();
+        prom::MASTER_BRIGHTNESS.set(1.0);
+
+        Ok(runtime)
+    }
+
+    /// Loads all fixtures without running them, and logs every problem found, instead of stopping
+    /// at the first one.
+    /// Returns the number of problems.
+    pub(crate) fn check<P: AsRef<Path>>(
+        fixtures_roots: &[P],
+        universe_config: &UniverseConfig,
+        allow_output_overlap: bool,
+        alias_overrides: &HashMap<String, String>,
+    ) -> Result<usize> {
+        let LoadedFixtures {
+            bundles: _bundles,
+            mut fixtures,
+            failed,
+        } = load_fixtures(
+            fixtures_roots,
+            universe_config,
+            alias_overrides,
+            &SelectedPrograms::default(),
+        )?;
+
+        let mut problems = failed.len();
+        for (path, err) in failed.iter() {
+            error!("unable to load fixture at {:?}: {:#}", path, err);
+        }
+        for fixture in fixtures.iter() {
+            info!(
+                "loaded fixture {} from {:?} with {} programs",
+                fixture.name,
+                fixture.source_path,
+                fixture.num_programs()
+            );
+        }
+
+        if let Err(err) = check_duplicate_names(&fixtures) {
+            error!("{:#}", err);
+            problems += 1;
+        }
+        fixtures.sort_by_key(|f| f.priority);
+        if let Err(err) = check_output_overlap(&fixtures, allow_output_overlap) {
+            error!("{:#}", err);
+            problems += 1;
+        }
+
+        Ok(problems)
     }
 
+    /// Updates the number of loaded and active programs, after fixtures were added or removed.
+    fn update_program_metrics(&self) {
+        prom::LOADED_PROGRAMS.set(
+            self.fixtures
+                .iter()
+                .map(|f| f.inner.num_programs())
+                .sum::<usize>() as f64,
+        );
+        // Every fixture has exactly one active program.
+        prom::ACTIVE_PROGRAMS.set(self.fixtures.len() as f64);
+    }
+
+    /// Updates the snapshot of selected programs readable by Lua programs, if it changed.
+    fn update_selected_programs(&self) {
+        let changed = {
+            let snapshot = self.selected_programs.read().unwrap();
+            snapshot.len() != self.fixtures.len()
+                || self.fixtures.iter().any(|f| {
+                    snapshot.get(&f.inner.name).map(String::as_str)
+                        != Some(f.inner.active_program())
+                })
+        };
+        if !changed {
+            return;
+        }
+
+        *self.selected_programs.write().unwrap() = self
+            .fixtures
+            .iter()
+            .map(|f| (f.inner.name.clone(), f.inner.active_program().to_string()))
+            .collect();
+    }
+
+    /// Returns the times of sunrise and sunset on the given date, if a location is configured.
+    /// These only change once per day, so they are cached.
+    fn sun_times_on(&mut self, date: NaiveDate) -> Option<SunTimes> {
+        let location = self.options.location?;
+        match self.sun_times {
+            Some((cached_date, sun_times)) if cached_date == date => sun_times,
+            _ => {
+                let sun_times = sun::sun_times(date, location.latitude, location.longitude);
+                info!("sunrise and sunset on {}: {:?}", date, sun_times);
+                self.sun_times = Some((date, sun_times));
+                sun_times
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub(crate) fn tick(&mut self) -> Result<&[SetRequest]> {
         self.set_requests.clear();
+        self.set_requests.append(&mut self.queued_set_requests);
+
+        self.update_selected_programs();
 
         let now = Instant::now();
         let dt = Local::now();
         let ts = TickState {
             timestamp: now.clone(),
             local_time: dt,
+            sun_times: self.sun_times_on(dt.date_naive()),
         };
 
-        for fixture in self.fixtures.iter_mut() {
-            match fixture.tick(&ts) {
+        let tick_fixture = |fixture: &mut WrappedFixture| {
+            // Tick errors are part of the metadata.
+            let was_failing = fixture.tick_errors.consecutive > 0;
+            (was_failing, fixture.tick(&ts))
+        };
+        // Fixtures are independent of each other, so they can be ticked in parallel.
+        // Results are handled afterwards, in fixture order, to keep outputs deterministic.
+        let results: Vec<_> = if self.options.parallel_ticks {
+            self.fixtures.par_iter_mut().map(tick_fixture).collect()
+        } else {
+            self.fixtures.iter_mut().map(tick_fixture).collect()
+        };
+
+        for (fixture, (was_failing, res)) in self.fixtures.iter().zip(results) {
+            match res {
                 Err(err) => {
-                    warn!("unable to tick fixture {}: {:?}", fixture.inner.name, err)
+                    self.warnings.warn(
+                        &format!("unable to tick fixture {}", fixture.inner.name),
+                        &format!("{:?}", err),
+                    );
+                    fixture.tick_error_counter.inc();
+                    self.metadata_cache = None;
+                }
+                Ok(()) => {
+                    if was_failing {
+                        self.metadata_cache = None;
+                    }
+                    if !fixture.undeclared_outputs.is_empty() {
+                        self.warnings.warn(
+                            &format!("fixture {}", fixture.inner.name),
+                            &format!(
+                                "program {} wrote undeclared outputs {:?}, which were dropped",
+                                fixture.inner.active_program(),
+                                fixture.undeclared_outputs
+                            ),
+                        );
+                    }
+                    if let (Some(sent), true) = (&mut self.sent_values, fixture.switched_program) {
+                        // Outputs might have been changed externally, e.g., from EXTERNAL.
+                        for addr in fixture.inner.addresses.iter() {
+                            sent.remove(addr);
+                        }
+                    }
+                    let start = self.set_requests.len();
+                    self.set_requests
+                        .extend(fixture.set_requests.iter().cloned());
+                    scale_outputs(
+                        &mut self.set_requests[start..],
+                        self.master_brightness,
+                        &fixture.inner.binary_outputs,
+                    );
+                    if !fixture.disabled {
+                        fixture.tick_counter.inc();
+                    }
                 }
-                Ok(res) => self.set_requests.extend(res.iter().cloned()),
             }
         }
+        if let Some(solo) = &self.solo {
+            apply_solo(&mut self.set_requests, &self.fixtures, solo);
+        }
+        sort_and_dedup(&mut self.set_requests);
+        if let Some(sent) = &mut self.sent_values {
+            skip_unchanged(&mut self.set_requests, sent, self.resend_all);
+            self.resend_all = false;
+        }
         debug!("tick took {}µs", now.elapsed().as_micros());
         debug!("tick produced set requests {:?}", self.set_requests);
 
         Ok(&self.set_requests)
     }
 
-    pub(crate) fn alloy_metadata(
+    /// Sends the last value of every address again with the next tick, if only changed values are
+    /// sent.
+    /// This is necessary if set requests were lost, e.g., while Submarine was unreachable.
+    pub(crate) fn resend_all_outputs(&mut self) {
+        self.resend_all = true;
+    }
+
+    /// Builds metadata about all fixtures, including runtime state.
+    /// If a tag is given, only fixtures carrying that tag are included.
+    pub(crate) fn metadata(
         &self,
         universe: &UniverseConfig,
-    ) -> alloy::program::KaleidoscopeMetadata {
-        alloy::program::KaleidoscopeMetadata {
+        tag: Option<&str>,
+    ) -> KaleidoscopeMetadata {
+        KaleidoscopeMetadata {
             fixtures: self
                 .fixtures
                 .iter()
-                .map(|f| &f.inner)
-                .map(|f| (f.name.clone(), f.alloy_metadata(universe)))
+                .filter(|f| tag.map_or(true, |tag| f.inner.has_tag(tag)))
+                .map(|f| (f.inner.name.clone(), f.metadata(universe)))
                 .collect(),
+            paused: self.paused,
+            solo: self.solo.clone(),
         }
     }
 
+    /// Returns the serialized metadata about all fixtures.
+    /// This is cached until the next change to any fixture, which makes repeated polling cheap.
+    pub(crate) fn metadata_json(&mut self, universe: &UniverseConfig) -> Result<Arc<String>> {
+        if let Some(cached) = &self.metadata_cache {
+            return Ok(cached.clone());
+        }
+
+        debug!("rebuilding metadata cache");
+        let json = serde_json::to_string(&self.metadata(universe, None))
+            .context("unable to serialize metadata")?;
+        let json = Arc::new(json);
+        self.metadata_cache = Some(json.clone());
+
+        Ok(json)
+    }
+
+    /// Builds a feed of the last value of every fixture output, including declared positions.
+    /// Outputs are listed per fixture, in tick order, and by ascending address.
+    pub(crate) fn preview(&self, universe: &UniverseConfig) -> PreviewFeed {
+        let mut outputs = Vec::new();
+        for fixture in self.fixtures.iter() {
+            let mut fixture_outputs: Vec<_> = universe
+                .devices
+                .iter()
+                .flat_map(|d| &d.outputs)
+                .filter(|o| fixture.inner.addresses.contains(&o.address))
+                .map(|o| PreviewOutput {
+                    fixture: fixture.inner.name.clone(),
+                    alias: o.alias.clone(),
+                    address: o.address,
+                    position: fixture.inner.positions.get(&o.address).copied(),
+                    value: fixture.outputs.values.get(&o.address).copied(),
+                })
+                .collect();
+            fixture_outputs.sort_by_key(|o| o.address);
+            outputs.extend(fixture_outputs);
+        }
+
+        PreviewFeed { outputs }
+    }
+
+    /// Returns whether the tick loop is paused.
+    /// While paused, no ticks are executed and no set requests are sent, i.e., all outputs hold
+    /// their current values.
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub(crate) fn master_brightness(&self) -> f64 {
+        self.master_brightness
+    }
+
+    /// Sets the factor by which the outputs of all fixtures are scaled, except for binary
+    /// outputs.
+    /// The most recent outputs of all fixtures are sent again with the new brightness with the
+    /// next tick, since programs don't necessarily produce outputs on every tick.
+    pub(crate) fn set_master_brightness(&mut self, brightness: f64) -> Result<()> {
+        ensure!(
+            (0.0..=1.0).contains(&brightness),
+            RuntimeError::OutOfRange {
+                lower: 0.0,
+                upper: 1.0,
+                value: brightness,
+            }
+        );
+        if brightness == self.master_brightness {
+            return Ok(());
+        }
+        info!("setting master brightness to {}", brightness);
+        self.master_brightness = brightness;
+        prom::MASTER_BRIGHTNESS.set(brightness);
+        self.queue_last_outputs();
+
+        Ok(())
+    }
+
+    /// Queues the most recent outputs of all fixtures to be sent again with the next tick, since
+    /// programs don't necessarily produce outputs on every tick.
+    /// Fixtures running EXTERNAL are skipped, since their outputs are controlled elsewhere.
+    fn queue_last_outputs(&mut self) {
+        for fixture in self.fixtures.iter_mut() {
+            if fixture.inner.active_program() == "EXTERNAL" {
+                continue;
+            }
+            let start = self.queued_set_requests.len();
+            self.queued_set_requests
+                .extend(
+                    fixture
+                        .outputs
+                        .values
+                        .iter()
+                        .map(|(addr, value)| SetRequest {
+                            value: *value,
+                            target: SetRequestTarget::Address(*addr),
+                        }),
+                );
+            scale_outputs(
+                &mut self.queued_set_requests[start..],
+                self.master_brightness,
+                &fixture.inner.binary_outputs,
+            );
+            // Unchanged outputs of the fixture must be sent again, too.
+            if let Some(previous) = &mut fixture.previous_set_requests {
+                previous.clear();
+            }
+        }
+    }
+
+    /// Makes the named fixture the only one producing outputs, or clears this if `None`.
+    /// The outputs of all other fixtures are held at LOW, regardless of their programs, until
+    /// this is cleared.
+    pub(crate) fn set_solo(
+        &mut self,
+        fixture: Option<&str>,
+    ) -> std::result::Result<(), RuntimeError> {
+        if let Some(name) = fixture {
+            if self.get_fixture(name).is_none() {
+                return Err(RuntimeError::UnknownFixture(name.to_string()));
+            }
+        }
+        if self.solo.as_deref() == fixture {
+            return Ok(());
+        }
+        match fixture {
+            Some(name) => info!("soloing fixture {}", name),
+            None => info!("clearing solo"),
+        }
+        self.solo = fixture.map(str::to_string);
+        self.metadata_cache = None;
+        // Fixtures which were held at LOW resume with their current outputs.
+        self.queue_last_outputs();
+
+        Ok(())
+    }
+
+    pub(crate) fn set_paused(&mut self, paused: bool) {
+        if paused != self.paused {
+            info!("{} tick loop", if paused { "pausing" } else { "resuming" });
+            self.metadata_cache = None;
+        }
+        self.paused = paused
+    }
+
+    /// Builds metadata about a single fixture, including runtime state.
+    pub(crate) fn fixture_metadata(
+        &self,
+        name: &str,
+        universe: &UniverseConfig,
+    ) -> Option<FixtureMetadata> {
+        self.fixtures
+            .iter()
+            .find(|f| f.inner.name == name)
+            .map(|f| f.metadata(universe))
+    }
+
+    /// Returns the most recent output values of a fixture.
+    pub(crate) fn fixture_outputs(&self, name: &str) -> Option<FixtureOutputs> {
+        self.fixtures
+            .iter()
+            .find(|f| f.inner.name == name)
+            .map(|f| f.outputs.clone())
+    }
+
+    /// Computes the outputs a program of a fixture would produce, without sending them or
+    /// modifying the fixture. See [Fixture::preview].
+    pub(crate) fn preview_program(
+        &mut self,
+        fixture: &str,
+        program: &str,
+        parameters: BTreeMap<String, ParameterRequest>,
+        at: Duration,
+    ) -> Result<Vec<SetRequest>> {
+        let dt = Local::now();
+        let sun_times = self.sun_times_on(dt.date_naive());
+        let fixture = self
+            .get_fixture(fixture)
+            .ok_or_else(|| RuntimeError::UnknownFixture(fixture.to_string()))?;
+        fixture.preview(program, parameters, at, dt, sun_times)
+    }
+
     pub(crate) fn get_fixture(&self, name: &str) -> Option<&Fixture> {
         self.fixtures
             .iter()
@@ -127,10 +908,1318 @@ impl Runtime {
             .map(|f| &f.inner)
     }
 
+    /// Returns the fixture with the given name for modification.
+    /// This invalidates cached metadata and marks the state of fixtures as changed.
     pub(crate) fn get_fixture_mut(&mut self, name: &str) -> Option<&mut Fixture> {
+        self.metadata_cache = None;
+        self.state_dirty = true;
         self.fixtures
             .iter_mut()
             .find(|f| f.inner.name == name)
             .map(|f| &mut f.inner)
     }
+
+    /// Overrides the duration of the crossfade for the next program switch of a fixture.
+    pub(crate) fn set_next_program_fade(
+        &mut self,
+        name: &str,
+        duration: Duration,
+    ) -> std::result::Result<(), RuntimeError> {
+        let fixture = self
+            .fixtures
+            .iter_mut()
+            .find(|f| f.inner.name == name)
+            .ok_or_else(|| RuntimeError::UnknownFixture(name.to_string()))?;
+        fixture.next_program_fade = Some(duration);
+        Ok(())
+    }
+
+    /// Enables or disables a fixture.
+    /// Disabled fixtures are skipped during ticks, i.e., their outputs stay at their last values,
+    /// regardless of the selected program.
+    pub(crate) fn set_fixture_enabled(
+        &mut self,
+        name: &str,
+        enabled: bool,
+    ) -> std::result::Result<(), RuntimeError> {
+        let fixture = self
+            .fixtures
+            .iter_mut()
+            .find(|f| f.inner.name == name)
+            .ok_or_else(|| RuntimeError::UnknownFixture(name.to_string()))?;
+        if fixture.disabled == enabled {
+            info!(
+                "{} fixture {}",
+                if enabled { "enabling" } else { "disabling" },
+                name
+            );
+            fixture.disabled = !enabled;
+            self.metadata_cache = None;
+        }
+        Ok(())
+    }
+
+    /// Returns the names of all fixtures carrying the given tag, in tick order.
+    pub(crate) fn fixtures_with_tag(&self, tag: &str) -> Vec<String> {
+        self.fixtures
+            .iter()
+            .filter(|f| f.inner.has_tag(tag))
+            .map(|f| f.inner.name.clone())
+            .collect()
+    }
+
+    /// Sets the active program of the named fixtures, or of all fixtures if `fixtures` is `None`.
+    /// Fixtures which don't have the program keep their current program.
+    /// Returns the outcome per fixture.
+    pub(crate) fn set_active_program_many(
+        &mut self,
+        program: &str,
+        fixtures: Option<&[String]>,
+    ) -> BTreeMap<String, std::result::Result<(), RuntimeError>> {
+        self.metadata_cache = None;
+        self.state_dirty = true;
+        let names: Vec<String> = match fixtures {
+            Some(names) => names.to_vec(),
+            None => self.fixtures.iter().map(|f| f.inner.name.clone()).collect(),
+        };
+
+        names
+            .into_iter()
+            .map(|name| {
+                let res = match self.fixtures.iter_mut().find(|f| f.inner.name == name) {
+                    Some(fixture) => fixture.inner.set_active_program(program),
+                    None => Err(RuntimeError::UnknownFixture(name.clone())),
+                };
+                (name, res)
+            })
+            .collect()
+    }
+
+    /// Removes the fixture with the given name.
+    /// If `blackout` is set, all outputs of the fixture are set to LOW with the next tick.
+    pub(crate) fn remove_fixture(
+        &mut self,
+        name: &str,
+        blackout: bool,
+    ) -> std::result::Result<(), RuntimeError> {
+        let pos = self
+            .fixtures
+            .iter()
+            .position(|f| f.inner.name == name)
+            .ok_or_else(|| RuntimeError::UnknownFixture(name.to_string()))?;
+        let fixture = self.fixtures.remove(pos);
+        // Don't keep exporting metrics for removed fixtures.
+        let _ = prom::FIXTURE_TICKS.remove_label_values(&[name]);
+        let _ = prom::FIXTURE_TICK_ERRORS.remove_label_values(&[name]);
+        fixture.inner.remove_metrics();
+        self.update_program_metrics();
+        self.metadata_cache = None;
+        info!("removed fixture {}", name);
+        if self.solo.as_deref() == Some(name) {
+            self.set_solo(None)?;
+        }
+
+        if blackout {
+            self.queued_set_requests
+                .extend(fixture.inner.addresses.iter().map(|addr| SetRequest {
+                    value: LOW,
+                    target: SetRequestTarget::Address(*addr),
+                }));
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the fixture of the same name with the given fixture.
+    /// The currently selected program is kept, if the new fixture still defines it.
+    fn replace_fixture(&mut self, mut fixture: Fixture) -> Result<()> {
+        let wrapped = self
+            .fixtures
+            .iter_mut()
+            .find(|f| f.inner.name == fixture.name)
+            .ok_or_else(|| RuntimeError::UnknownFixture(fixture.name.clone()))?;
+        // The new fixture might not define all programs of the old one.
+        wrapped.inner.remove_metrics();
+        fixture.migrate_parameters_from(&wrapped.inner);
+        let disabled = wrapped.disabled;
+        let selected_program = wrapped.inner.active_program();
+        if let Err(err) = fixture.set_active_program(selected_program) {
+            info!(
+                "{}: not keeping selected program after reload: {}",
+                fixture.name, err
+            );
+        }
+        *wrapped = WrappedFixture::wrap(fixture, &self.options, &self.events);
+        wrapped.disabled = disabled;
+        wrapped.inner.export_metrics();
+        self.metadata_cache = None;
+        self.state_dirty = true;
+
+        // The priority might have changed.
+        self.fixtures.sort_by_key(|f| f.inner.priority);
+        self.update_program_metrics();
+
+        Ok(())
+    }
+
+    /// Reloads a Lua program of a fixture from its source, see [Fixture::reload_program].
+    pub(crate) fn reload_program(&mut self, fixture: &str, program: &str) -> Result<()> {
+        let fixture = self
+            .get_fixture_mut(fixture)
+            .ok_or_else(|| RuntimeError::UnknownFixture(fixture.to_string()))?;
+        fixture.reload_program(program)?;
+        self.metadata_cache = None;
+        self.state_dirty = true;
+
+        Ok(())
+    }
+
+    /// Subscribes to program switches and parameter changes of all fixtures.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<StateEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns the selected programs and parameter values of all fixtures.
+    /// Taken together with [Runtime::subscribe], the snapshot includes all changes before the
+    /// first event received.
+    pub(crate) fn snapshot(&self) -> StateEvent {
+        StateEvent::Snapshot {
+            fixtures: self
+                .fixtures
+                .iter()
+                .map(|f| (f.inner.name.clone(), f.inner.export_state()))
+                .collect(),
+        }
+    }
+
+    /// Saves the selected programs and parameter values of all fixtures to the state file, if
+    /// one is configured and anything changed since the last save.
+    pub(crate) fn save_state_if_changed(&mut self) -> Result<()> {
+        let state_file = match &self.options.state_file {
+            Some(f) if self.state_dirty => f,
+            _ => return Ok(()),
+        };
+
+        let state: BTreeMap<_, _> = self
+            .fixtures
+            .iter()
+            .map(|f| (f.inner.name.clone(), f.inner.export_state()))
+            .collect();
+        let json = serde_json::to_vec_pretty(&state).context("unable to serialize state")?;
+
+        // Write to a temporary file first, so that a crash doesn't leave a truncated file behind.
+        let tmp_file = state_file.with_extension("tmp");
+        fs::write(&tmp_file, json).context(format!("unable to write {:?}", tmp_file))?;
+        fs::rename(&tmp_file, state_file).context(format!("unable to replace {:?}", state_file))?;
+        debug!("saved state to {:?}", state_file);
+
+        self.state_dirty = false;
+        Ok(())
+    }
+
+    /// Returns the number of loaded fixtures.
+    pub(crate) fn num_fixtures(&self) -> usize {
+        self.fixtures.len()
+    }
+
+    /// Returns whether fixtures were loaded from a bundle, as opposed to a directory.
+    pub(crate) fn is_bundled(&self) -> bool {
+        !self.bundles.is_empty()
+    }
+
+    /// Returns the names of all fixtures whose source or program sources are among the given
+    /// paths.
+    pub(crate) fn fixtures_affected_by(&self, paths: &HashSet<PathBuf>) -> Vec<String> {
+        // Fixture paths are relative to the working directory, while watchers usually report
+        // absolute paths.
+        let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        let paths: HashSet<_> = paths.iter().map(|p| canonical(p)).collect();
+
+        self.fixtures
+            .iter()
+            .filter(|f| {
+                std::iter::once(&f.inner.source_path)
+                    .chain(f.inner.program_paths.iter())
+                    .any(|p| paths.contains(&canonical(p)))
+            })
+            .map(|f| f.inner.name.clone())
+            .collect()
+    }
+
+    /// Reloads the fixture with the given name from its source file.
+    ///
+    /// Loading and setting up the fixture is expensive, since it reads from disk and executes
+    /// Lua. This is done without holding the lock on the runtime, which is only taken briefly to
+    /// look up the source path and to swap in the new fixture. Ticks thus continue during the
+    /// reload.
+    pub(crate) async fn reload_fixture(
+        runtime: &Mutex<Runtime>,
+        name: &str,
+        universe_config: Arc<UniverseConfig>,
+    ) -> Result<()> {
+        let (source_path, alias_overrides, selected_programs) = {
+            let runtime = runtime.lock().await;
+            let source_path = runtime
+                .get_fixture(name)
+                .ok_or_else(|| RuntimeError::UnknownFixture(name.to_string()))?
+                .source_path
+                .clone();
+            (
+                source_path,
+                runtime.options.alias_overrides.clone(),
+                runtime.selected_programs.clone(),
+            )
+        };
+
+        info!("reloading fixture {} from {:?}...", name, source_path);
+        let fixture = {
+            let source_path = source_path.clone();
+            task::spawn_blocking(move || {
+                Fixture::new(
+                    &source_path,
+                    &universe_config,
+                    &alias_overrides,
+                    &selected_programs,
+                )
+            })
+        }
+        .await
+        .context("fixture loader panicked")?
+        .context(format!("unable to load fixture at {:?}", &source_path))?;
+        ensure!(
+            fixture.name == name,
+            "fixture at {:?} was renamed from {} to {}",
+            source_path,
+            name,
+            fixture.name
+        );
+
+        runtime.lock().await.replace_fixture(fixture)
+    }
+
+    /// Reloads all fixtures against the given universe config, e.g., after it changed.
+    /// Fixtures which fail to reload keep running in their previous version.
+    /// Returns the number of fixtures which failed to reload.
+    pub(crate) async fn reload_all_fixtures(
+        runtime: &Mutex<Runtime>,
+        universe_config: Arc<UniverseConfig>,
+    ) -> usize {
+        let names: Vec<String> = runtime
+            .lock()
+            .await
+            .fixtures
+            .iter()
+            .map(|f| f.inner.name.clone())
+            .collect();
+
+        let mut failed = 0;
+        for name in names {
+            if let Err(err) = Runtime::reload_fixture(runtime, &name, universe_config.clone()).await
+            {
+                warn!("unable to reload fixture {}: {:?}", name, err);
+                failed += 1;
+            }
+        }
+
+        failed
+    }
+}
+
+/// Fixtures loaded from one or more fixtures roots, before any checks across fixtures.
+struct LoadedFixtures {
+    /// The extracted fixtures bundles, which must be kept for as long as fixtures are used.
+    bundles: Vec<ExtractedBundle>,
+    fixtures: Vec<Fixture>,
+    /// Sources which failed to load and are not used as a program by any fixture.
+    failed: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// Loads all fixtures from the given roots.
+/// Failing to list or extract a root is an error, failing to load a fixture is not.
+/// The initial program of every fixture is recorded in the given snapshot before any programs are
+/// loaded.
+fn load_fixtures<P: AsRef<Path>>(
+    fixtures_roots: &[P],
+    universe_config: &UniverseConfig,
+    alias_overrides: &HashMap<String, String>,
+    selected_programs: &SelectedPrograms,
+) -> Result<LoadedFixtures> {
+    ensure!(!fixtures_roots.is_empty(), "no fixtures path configured");
+    fixture::check_alias_overrides(universe_config, alias_overrides);
+
+    let mut bundles = Vec::new();
+    let mut sources = Vec::new();
+    for fixtures_root in fixtures_roots.iter() {
+        let fixtures_root = fixtures_root.as_ref();
+        let bundle = bundle::extract_if_bundle(fixtures_root).context(format!(
+            "unable to extract fixtures bundle {:?}",
+            fixtures_root
+        ))?;
+        let root = match &bundle {
+            Some(bundle) => bundle.root(),
+            None => fixtures_root,
+        };
+        sources.extend(
+            find_lua_sources(root)
+                .context(format!("unable to list fixtures in {:?}", root))?
+                .into_iter()
+                .map(|path| {
+                    let depth = path
+                        .strip_prefix(root)
+                        .map_or(0, |p| p.components().count());
+                    (depth, path)
+                }),
+        );
+        bundles.extend(bundle);
+    }
+
+    // Programs live next to fixtures, so not every source is a fixture.
+    // Sources are set up shallowest first across all roots, which usually sets up fixtures
+    // before the sources of their programs, which we then skip.
+    // Only once the programs of all fixtures are known are any programs loaded, so that sources
+    // set up before the fixture using them as a program are never loaded as fixtures.
+    // Sources which fail to set up are only an error if no fixture uses them as a program,
+    // e.g., from a shared directory of programs.
+    sources.sort_by_key(|(depth, _)| *depth);
+    let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    let mut program_paths: HashSet<PathBuf> = HashSet::new();
+    let mut failed = Vec::new();
+    let mut setups: Vec<FixtureSetup> = Vec::new();
+    for (_, path) in sources {
+        if program_paths.contains(&canonical(&path)) {
+            continue;
+        }
+
+        match FixtureSetup::new(&path, universe_config, alias_overrides) {
+            Ok(setup) => {
+                program_paths.extend(setup.program_paths().map(|p| canonical(&p)));
+                setups.push(setup)
+            }
+            Err(err) => failed.push((path, err)),
+        }
+    }
+    let mut failed: Vec<_> = failed
+        .into_iter()
+        .filter(|(path, _)| !program_paths.contains(&canonical(path)))
+        .collect();
+
+    let (programs, setups): (Vec<_>, Vec<_>) = setups
+        .into_iter()
+        .partition(|setup| program_paths.contains(&canonical(setup.source_path())));
+    for program in programs {
+        debug!("{:?} is a program, not a fixture", program.source_path());
+    }
+    *selected_programs.write().unwrap() = setups
+        .iter()
+        .map(|setup| {
+            (
+                setup.name().to_string(),
+                setup.initial_program().to_string(),
+            )
+        })
+        .collect();
+
+    let mut fixtures: Vec<Fixture> = Vec::new();
+    for setup in setups {
+        let path = setup.source_path().to_path_buf();
+        match Fixture::load(setup, universe_config, alias_overrides, selected_programs) {
+            Ok(fix) => fixtures.push(fix),
+            Err(err) => failed.push((path, err)),
+        }
+    }
+
+    Ok(LoadedFixtures {
+        bundles,
+        fixtures,
+        failed,
+    })
+}
+
+/// Checks that fixture names are unique.
+fn check_duplicate_names(fixtures: &[Fixture]) -> Result<()> {
+    for (i, fix) in fixtures.iter().enumerate() {
+        if let Some(f) = fixtures[..i].iter().find(|f| f.name == fix.name) {
+            bail!(
+                "duplicate fixture: {} in file {:?} (other was {:?})",
+                fix.name,
+                &fix.source_path,
+                &f.source_path
+            )
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that no two fixtures drive the same output address, which would otherwise be set by
+/// whichever of them is ticked last.
+/// If overlap is allowed, each conflict is logged instead.
+fn check_output_overlap(fixtures: &[Fixture], allow_overlap: bool) -> Result<()> {
+    let mut conflicts = Vec::new();
+    for (i, a) in fixtures.iter().enumerate() {
+        // Fixtures are in tick order, so b is ticked after a.
+        for b in fixtures[i + 1..].iter() {
+            let mut shared: Vec<_> = a.addresses.intersection(&b.addresses).copied().collect();
+            if shared.is_empty() {
+                continue;
+            }
+            shared.sort_unstable();
+            conflicts.push((a, b, shared));
+        }
+    }
+
+    if allow_overlap {
+        for (a, b, shared) in conflicts.iter() {
+            warn!(
+                "fixtures {} and {} both drive outputs {:?}, {} takes precedence",
+                a.name, b.name, shared, b.name
+            );
+        }
+        return Ok(());
+    }
+
+    if !conflicts.is_empty() {
+        bail!(
+            "fixtures drive the same outputs: {}",
+            conflicts
+                .iter()
+                .map(|(a, b, shared)| format!("{} and {}: {:?}", a.name, b.name, shared))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+
+    Ok(())
+}
+
+/// Finds all Lua sources in the given directory and its subdirectories, shallowest first and
+/// sorted by path within each directory.
+fn find_lua_sources(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut sources = Vec::new();
+    let mut dirs = VecDeque::from([root.to_path_buf()]);
+    while let Some(dir) = dirs.pop_front() {
+        let mut entries = fs::read_dir(&dir)
+            .context(format!("unable to list {:?}", dir))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .context(format!("unable to enumerate {:?}", dir))?;
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                dirs.push_back(path);
+            } else if path.extension().map_or(false, |ext| ext == "lua") {
+                sources.push(path);
+            }
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Restores the selected programs and parameter values of fixtures from a state file.
+/// A missing or corrupt file is logged and otherwise ignored, as are entries which no longer
+/// apply to the loaded fixtures.
+fn restore_state(state_file: &Path, fixtures: &mut [Fixture]) {
+    let contents = match fs::read(state_file) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            info!("no saved state at {:?}, starting fresh", state_file);
+            return;
+        }
+        Err(err) => {
+            warn!(
+                "unable to read state from {:?}, starting fresh: {}",
+                state_file, err
+            );
+            return;
+        }
+    };
+    let mut state: HashMap<String, FixtureState> = match serde_json::from_slice(&contents) {
+        Ok(state) => state,
+        Err(err) => {
+            warn!(
+                "unable to parse state from {:?}, starting fresh: {}",
+                state_file, err
+            );
+            return;
+        }
+    };
+
+    for fixture in fixtures.iter_mut() {
+        if let Some(fixture_state) = state.remove(&fixture.name) {
+            fixture.restore_state(fixture_state);
+        }
+    }
+    for name in state.keys() {
+        debug!("skipping saved state of unknown fixture {}", name);
+    }
+    info!("restored state from {:?}", state_file);
+}
+
+/// Replaces the outputs of all fixtures except the soloed one with LOW.
+/// Addresses shared with the soloed fixture are left to it.
+fn apply_solo(set_requests: &mut Vec<SetRequest>, fixtures: &[WrappedFixture], solo: &str) {
+    let soloed: HashSet<Address> = fixtures
+        .iter()
+        .find(|f| f.inner.name == solo)
+        .map(|f| f.inner.addresses.iter().copied().collect())
+        .unwrap_or_default();
+    set_requests.retain(|req| match req.target {
+        SetRequestTarget::Address(addr) => soloed.contains(&addr),
+        _ => true,
+    });
+    let others: BTreeSet<Address> = fixtures
+        .iter()
+        .filter(|f| f.inner.name != solo)
+        .flat_map(|f| f.inner.addresses.iter().copied())
+        .filter(|addr| !soloed.contains(addr))
+        .collect();
+    set_requests.extend(others.into_iter().map(|addr| SetRequest {
+        value: LOW,
+        target: SetRequestTarget::Address(addr),
+    }));
+}
+
+/// Scales the values of set requests by the master brightness, except for binary outputs.
+fn scale_outputs(set_requests: &mut [SetRequest], brightness: f64, binary: &HashSet<Address>) {
+    if brightness >= 1.0 {
+        return;
+    }
+    for req in set_requests.iter_mut() {
+        if let SetRequestTarget::Address(addr) = req.target {
+            if !binary.contains(&addr) {
+                req.value = (req.value as f64 * brightness).round() as OutputValue;
+            }
+        }
+    }
+}
+
+/// Sorts set requests by address and removes all but the last request for each address.
+/// Since fixtures are ticked in order of ascending priority, this means that the fixture with
+/// the highest priority wins.
+/// Requests not targeting an address are kept in their original order, after all others.
+fn sort_and_dedup(set_requests: &mut Vec<SetRequest>) {
+    set_requests.sort_by_key(sort_key);
+
+    // dedup_by keeps the first of consecutive duplicates, so we reverse around it to keep the
+    // last one. The sort is stable, so this is the one written last.
+    set_requests.reverse();
+    set_requests.dedup_by(|a, b| match (&a.target, &b.target) {
+        (SetRequestTarget::Address(a), SetRequestTarget::Address(b)) => a == b,
+        _ => false,
+    });
+    set_requests.reverse();
+}
+
+/// Removes set requests which set an address to the value last sent for it, and records the
+/// values of the remaining ones.
+/// If `resend_all` is set, nothing is removed, and the last values of all addresses not set
+/// otherwise are added.
+/// The set requests must be sorted and deduplicated.
+fn skip_unchanged(
+    set_requests: &mut Vec<SetRequest>,
+    sent: &mut HashMap<Address, OutputValue>,
+    resend_all: bool,
+) {
+    if resend_all {
+        let set: HashSet<_> = set_requests
+            .iter()
+            .filter_map(|req| match &req.target {
+                SetRequestTarget::Address(addr) => Some(*addr),
+                _ => None,
+            })
+            .collect();
+        set_requests.extend(sent.iter().filter(|(addr, _)| !set.contains(*addr)).map(
+            |(addr, value)| SetRequest {
+                value: *value,
+                target: SetRequestTarget::Address(*addr),
+            },
+        ));
+        set_requests.sort_by_key(sort_key);
+    } else {
+        set_requests.retain(|req| match &req.target {
+            SetRequestTarget::Address(addr) => sent.get(addr) != Some(&req.value),
+            _ => true,
+        });
+    }
+
+    for req in set_requests.iter() {
+        if let SetRequestTarget::Address(addr) = req.target {
+            sent.insert(addr, req.value);
+        }
+    }
+}
+
+/// Orders set requests by address, followed by requests not targeting an address.
+fn sort_key(req: &SetRequest) -> (bool, Address) {
+    match &req.target {
+        SetRequestTarget::Address(addr) => (false, *addr),
+        _ => (true, Default::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_util::{self, constant_program, runtime_with_program, FixturesDir};
+
+    fn set(addr: Address, value: OutputValue) -> SetRequest {
+        SetRequest {
+            value,
+            target: SetRequestTarget::Address(addr),
+        }
+    }
+
+    fn values(set_requests: &[SetRequest]) -> Vec<(Address, OutputValue)> {
+        set_requests
+            .iter()
+            .map(|req| match req.target {
+                SetRequestTarget::Address(addr) => (addr, req.value),
+                _ => panic!("unexpected target {:?}", req.target),
+            })
+            .collect()
+    }
+
+    fn wrapped<'a>(runtime: &'a Runtime, name: &str) -> &'a WrappedFixture {
+        runtime
+            .fixtures
+            .iter()
+            .find(|f| f.inner.name == name)
+            .unwrap()
+    }
+
+    /// Returns the metadata of a runtime set up by [runtime_with_program].
+    fn metadata(runtime: &mut Runtime) -> serde_json::Value {
+        let universe = test_util::universe_with_aliases(&["a"]);
+        serde_json::from_str(&runtime.metadata_json(&universe).unwrap()).unwrap()
+    }
+
+    fn addresses(runtime: &Runtime, fixture: &str) -> BTreeSet<Address> {
+        let fixture = runtime.get_fixture(fixture).unwrap();
+        fixture.addresses.iter().copied().collect()
+    }
+
+    #[tokio::test]
+    async fn reload_all_fixtures_revalidates_against_new_universe() {
+        let dir = FixturesDir::new();
+        dir.fixture("f", r#"add_output_alias("a")"#);
+        let old_universe = Arc::new(test_util::universe_with_aliases(&["a"]));
+        let runtime = Mutex::new(dir.runtime(&old_universe, test_util::options()));
+
+        // The new output is not part of the universe yet.
+        dir.fixture("f", r#"add_output_alias("a") add_output_alias("extra")"#);
+        assert_eq!(
+            Runtime::reload_all_fixtures(&runtime, old_universe.clone()).await,
+            1
+        );
+        assert_eq!(addresses(&*runtime.lock().await, "f"), BTreeSet::from([1]));
+
+        let new_universe = Arc::new(test_util::universe_with_aliases(&["a", "extra"]));
+        assert_eq!(
+            Runtime::reload_all_fixtures(&runtime, new_universe).await,
+            0
+        );
+        assert_eq!(
+            addresses(&*runtime.lock().await, "f"),
+            BTreeSet::from([1, 2])
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn ticks_continue_during_slow_reload() {
+        let dir = FixturesDir::new();
+        dir.fixture("f", r#"add_output_alias("a")"#);
+        let universe = Arc::new(test_util::universe_with_aliases(&["a"]));
+        let runtime = Arc::new(Mutex::new(dir.runtime(&universe, test_util::options())));
+
+        // Simulate a slow setup, which runs outside the lock during reloads.
+        dir.fixture(
+            "f",
+            r#"add_output_alias("a") local x = 0 for i = 1, 20000000 do x = x + i end"#,
+        );
+        let reload = {
+            let runtime = runtime.clone();
+            tokio::spawn(async move { Runtime::reload_fixture(&runtime, "f", universe).await })
+        };
+
+        let mut ticks = 0;
+        while !reload.is_finished() {
+            runtime.lock().await.tick().unwrap();
+            ticks += 1;
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        reload.await.unwrap().unwrap();
+        assert!(ticks > 0);
+    }
+
+    #[test]
+    fn tick_errors_are_counted() {
+        let (_dir, mut runtime) = runtime_with_program(
+            r#"function setup()
+end
+
+n = 0
+function tick(now)
+    n = n + 1
+    if n == 2 or n == 3 then
+        error("intermittent failure")
+    end
+    set_alias("a", HIGH)
+end
+"#,
+            test_util::options(),
+        );
+        let errors_before = wrapped(&runtime, "f").tick_error_counter.get();
+
+        let mut counts = Vec::new();
+        for _ in 0..4 {
+            runtime.tick().unwrap();
+            let errors = wrapped(&runtime, "f").tick_errors;
+            counts.push((errors.total, errors.consecutive));
+        }
+
+        assert_eq!(counts, vec![(0, 0), (1, 1), (2, 2), (2, 0)]);
+        let errors = wrapped(&runtime, "f").tick_error_counter.get() - errors_before;
+        assert_eq!(errors, 2);
+        let metadata = metadata(&mut runtime);
+        assert_eq!(
+            metadata["fixtures"]["f"]["tick_errors"],
+            serde_json::json!({"total": 2, "consecutive": 0})
+        );
+    }
+
+    #[test]
+    fn last_error_is_part_of_metadata() {
+        // Tick error metrics are shared by fixtures of the same name, so this uses its own name.
+        let dir = FixturesDir::new();
+        dir.fixture(
+            "failing",
+            r#"add_output_alias("a") add_program("p", "failing/p.lua")"#,
+        );
+        dir.program(
+            "failing/p.lua",
+            "function setup()\nend\n\nfunction tick(now)\n    error(\"broken program\")\nend\n",
+        );
+        let mut runtime = dir.runtime(
+            &test_util::universe_with_aliases(&["a"]),
+            test_util::options(),
+        );
+        runtime
+            .get_fixture_mut("failing")
+            .unwrap()
+            .set_active_program("p")
+            .unwrap();
+
+        assert!(metadata(&mut runtime)["fixtures"]["failing"]["last_error"].is_null());
+        runtime.tick().unwrap();
+        let failing = &metadata(&mut runtime)["fixtures"]["failing"];
+        assert!(
+            failing["last_error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("broken program"),
+            "{}",
+            failing
+        );
+        assert_eq!(failing["tick_errors"]["consecutive"], 1);
+
+        // The error is kept after a fix, but no longer counts as consecutive.
+        dir.program("failing/p.lua", &constant_program("a", HIGH));
+        runtime.reload_program("failing", "p").unwrap();
+        runtime.tick().unwrap();
+        let fixed = &metadata(&mut runtime)["fixtures"]["failing"];
+        assert_eq!(fixed["last_error"], failing["last_error"]);
+        assert_eq!(fixed["tick_errors"]["consecutive"], 0);
+    }
+
+    #[test]
+    fn manual_parameters_map_their_declared_range() {
+        let dir = FixturesDir::new();
+        dir.fixture(
+            "f",
+            r#"add_output_alias("a") set_manual_range("a", -10.0, 10.0)"#,
+        );
+        let mut runtime = dir.runtime(
+            &test_util::universe_with_aliases(&["a"]),
+            test_util::options(),
+        );
+        runtime
+            .get_fixture_mut("f")
+            .unwrap()
+            .set_active_program("MANUAL")
+            .unwrap();
+
+        let mut outputs = Vec::new();
+        for value in [-10.0, 0.0, 10.0] {
+            runtime
+                .get_fixture_mut("f")
+                .unwrap()
+                .get_program_mut("MANUAL")
+                .unwrap()
+                .get_parameter_mut("a")
+                .unwrap()
+                .set(ParameterRequest::Alloy(
+                    alloy::program::ParameterSetRequest::Continuous { value },
+                ))
+                .unwrap();
+            outputs.extend(values(runtime.tick().unwrap()));
+        }
+
+        let half = alloy::map_to_value((0.0, 1.0), 0.5);
+        assert_eq!(outputs, vec![(1, LOW), (1, half), (1, HIGH)]);
+    }
+
+    #[test]
+    fn metadata_cache_is_rebuilt_on_state_changes() {
+        let (_dir, mut runtime) =
+            runtime_with_program(&constant_program("a", HIGH), test_util::options());
+        let universe = test_util::universe_with_aliases(&["a"]);
+        let mut cached = runtime.metadata_json(&universe).unwrap();
+        let mut rebuilt = |runtime: &mut Runtime| {
+            let json = runtime.metadata_json(&universe).unwrap();
+            let rebuilt = !Arc::ptr_eq(&json, &cached);
+            cached = json;
+            rebuilt
+        };
+
+        assert!(!rebuilt(&mut runtime));
+        runtime.tick().unwrap();
+        assert!(!rebuilt(&mut runtime));
+
+        runtime.set_paused(true);
+        assert!(rebuilt(&mut runtime));
+        assert!(!rebuilt(&mut runtime));
+
+        runtime
+            .get_fixture_mut("f")
+            .unwrap()
+            .set_active_program("MANUAL")
+            .unwrap();
+        assert!(rebuilt(&mut runtime));
+        assert_eq!(
+            metadata(&mut runtime)["fixtures"]["f"]["selected_program"],
+            "MANUAL"
+        );
+
+        runtime.reload_program("f", "p").unwrap();
+        assert!(rebuilt(&mut runtime));
+        runtime.tick().unwrap();
+        assert!(!rebuilt(&mut runtime));
+    }
+
+    #[test]
+    fn unknown_fixtures_are_reported_as_runtime_errors() {
+        let (_dir, mut runtime) =
+            runtime_with_program(&constant_program("a", HIGH), test_util::options());
+
+        let err = runtime.reload_program("g", "p").unwrap_err();
+        assert!(matches!(
+            RuntimeError::find(&err),
+            Some(RuntimeError::UnknownFixture(name)) if name == "g"
+        ));
+        let err = runtime.reload_program("f", "q").unwrap_err();
+        assert!(matches!(
+            RuntimeError::find(&err),
+            Some(RuntimeError::UnknownProgram(name)) if name == "q"
+        ));
+    }
+
+    #[test]
+    fn preview_contains_positions_and_values() {
+        let dir = FixturesDir::new();
+        dir.fixture(
+            "f",
+            r#"add_output_alias("a") add_output_alias("b") set_position("a", 1.5, -2.0)
+add_program("p", "f/p.lua")"#,
+        );
+        dir.program("f/p.lua", &constant_program("a", HIGH));
+        let universe = test_util::universe_with_aliases(&["a", "b"]);
+        let mut runtime = dir.runtime(&universe, test_util::options());
+        runtime
+            .get_fixture_mut("f")
+            .unwrap()
+            .set_active_program("p")
+            .unwrap();
+        runtime.tick().unwrap();
+
+        assert_eq!(
+            serde_json::to_value(runtime.preview(&universe)).unwrap(),
+            serde_json::json!({
+                "outputs": [
+                    {
+                        "fixture": "f",
+                        "alias": "a",
+                        "address": 1,
+                        "position": {"x": 1.5, "y": -2.0, "z": 0.0},
+                        "value": HIGH,
+                    },
+                    {"fixture": "f", "alias": "b", "address": 2, "value": null},
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn tick_can_return_outputs_by_alias_and_address() {
+        let dir = FixturesDir::new();
+        dir.fixture(
+            "f",
+            r#"add_output_alias("a") add_output_alias("b") add_program("p", "f/p.lua")"#,
+        );
+        dir.program(
+            "f/p.lua",
+            "function setup()\nend\n\nfunction tick(now)\n    return { a = HIGH, [2] = LOW }\nend\n",
+        );
+        let mut runtime = dir.runtime(
+            &test_util::universe_with_aliases(&["a", "b"]),
+            test_util::options(),
+        );
+        runtime
+            .get_fixture_mut("f")
+            .unwrap()
+            .set_active_program("p")
+            .unwrap();
+
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, HIGH), (2, LOW)]);
+    }
+
+    #[test]
+    fn tick_return_values_other_than_tables_are_ignored() {
+        let (_dir, mut runtime) = runtime_with_program(
+            r#"function setup()
+end
+
+function tick(now)
+    set_alias("a", HIGH)
+    return 0
+end
+"#,
+            test_util::options(),
+        );
+
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, HIGH)]);
+    }
+
+    #[test]
+    fn shared_programs_are_not_loaded_as_fixtures() {
+        // This is valid both as a fixture and as a program.
+        let program = r#"function setup()
+    if fixture_name ~= nil then
+        fixture_name("impostor")
+    end
+end
+
+function tick(now)
+    set_alias("a", HIGH)
+end
+"#;
+        let programs = FixturesDir::new();
+        let top_level = programs.program("p.lua", program);
+        let nested = programs.program("shared/p.lua", program);
+        let fixtures = FixturesDir::new();
+        fixtures.fixture(
+            "f",
+            &format!(
+                r#"add_output_alias("a") add_program("p", "{}") add_program("q", "{}")"#,
+                top_level.display(),
+                nested.display()
+            ),
+        );
+
+        // The shared programs are listed first, at the same depth and deeper than the fixture.
+        let mut runtime = Runtime::new(
+            &[programs.path(), fixtures.path()],
+            &test_util::universe_with_aliases(&["a"]),
+            test_util::options(),
+        )
+        .unwrap();
+        let names: Vec<_> = runtime.fixtures.iter().map(|f| &f.inner.name).collect();
+        assert_eq!(names, vec!["f"]);
+
+        runtime
+            .get_fixture_mut("f")
+            .unwrap()
+            .set_active_program("q")
+            .unwrap();
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, HIGH)]);
+    }
+
+    #[test]
+    fn programs_read_selected_programs_of_their_runtime() {
+        let program = r#"function setup()
+    known_at_setup = pcall(get_fixture_program, "g")
+end
+
+function tick(now)
+    local ok, program = pcall(get_fixture_program, "g")
+    if not ok then
+        set_alias("a", 1)
+    elseif program == "ON" then
+        set_alias("a", HIGH)
+    else
+        set_alias("a", LOW)
+    end
+    set_alias("b", known_at_setup and HIGH or LOW)
+end
+"#;
+        let runtime = |with_g: bool| {
+            let dir = FixturesDir::new();
+            dir.fixture(
+                "f",
+                r#"add_output_alias("a") add_output_alias("b") add_program("p", "f/p.lua")"#,
+            );
+            dir.program("f/p.lua", program);
+            if with_g {
+                dir.fixture("g", r#"add_output_alias("c")"#);
+            }
+            let mut runtime = dir.runtime(
+                &test_util::universe_with_aliases(&["a", "b", "c"]),
+                test_util::options(),
+            );
+            runtime
+                .get_fixture_mut("f")
+                .unwrap()
+                .set_active_program("p")
+                .unwrap();
+            (dir, runtime)
+        };
+
+        let (_dir, mut with_g) = runtime(true);
+        assert_eq!(
+            values(with_g.tick().unwrap()),
+            vec![(1, LOW), (2, HIGH), (3, LOW)]
+        );
+        with_g
+            .get_fixture_mut("g")
+            .unwrap()
+            .set_active_program("ON")
+            .unwrap();
+        assert_eq!(
+            values(with_g.tick().unwrap()),
+            vec![(1, HIGH), (2, HIGH), (3, HIGH)]
+        );
+
+        // Other runtimes don't see g.
+        let (_dir, mut without_g) = runtime(false);
+        assert_eq!(values(without_g.tick().unwrap()), vec![(1, 1), (2, LOW)]);
+    }
+
+    #[test]
+    fn fixtures_tick_in_priority_order() {
+        let dir = FixturesDir::new();
+        for (name, priority, value) in [("a", 10, 3), ("b", 0, 1), ("c", 5, 2)] {
+            dir.fixture(
+                name,
+                &format!(
+                    r#"add_output_alias("a") set_priority({}) add_program("p", "{}/p.lua")"#,
+                    priority, name
+                ),
+            );
+            dir.program(&format!("{}/p.lua", name), &constant_program("a", value));
+        }
+        let mut runtime = dir.runtime(
+            &test_util::universe_with_aliases(&["a"]),
+            RuntimeOptions {
+                allow_output_overlap: true,
+                ..test_util::options()
+            },
+        );
+        for name in ["a", "b", "c"] {
+            let fixture = runtime.get_fixture_mut(name).unwrap();
+            fixture.set_active_program("p").unwrap();
+        }
+
+        let order: Vec<_> = runtime
+            .fixtures
+            .iter()
+            .map(|f| f.inner.name.as_str())
+            .collect();
+        assert_eq!(order, vec!["b", "c", "a"]);
+        // The fixture with the highest priority wins.
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn removed_fixture_emits_no_outputs() {
+        let dir = FixturesDir::new();
+        for (name, alias) in [("removed", "a"), ("kept", "b")] {
+            dir.fixture(
+                name,
+                &format!(
+                    r#"add_output_alias("{}") add_program("p", "{}/p.lua")"#,
+                    alias, name
+                ),
+            );
+            dir.program(&format!("{}/p.lua", name), &constant_program(alias, HIGH));
+        }
+        let universe = test_util::universe_with_aliases(&["a", "b"]);
+        let mut runtime = dir.runtime(&universe, test_util::options());
+        for name in ["removed", "kept"] {
+            let fixture = runtime.get_fixture_mut(name).unwrap();
+            fixture.set_active_program("p").unwrap();
+        }
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, HIGH), (2, HIGH)]);
+
+        runtime.remove_fixture("removed", true).unwrap();
+
+        let metadata: serde_json::Value =
+            serde_json::from_str(&runtime.metadata_json(&universe).unwrap()).unwrap();
+        assert!(metadata["fixtures"].get("removed").is_none());
+        assert!(metadata["fixtures"].get("kept").is_some());
+        // The outputs of the removed fixture are set to LOW once.
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, LOW), (2, HIGH)]);
+        assert_eq!(values(runtime.tick().unwrap()), vec![(2, HIGH)]);
+        assert!(matches!(
+            runtime.remove_fixture("removed", true),
+            Err(RuntimeError::UnknownFixture(_))
+        ));
+    }
+
+    #[test]
+    fn sort_and_dedup_keeps_last_request_per_address() {
+        let mut set_requests = vec![
+            set(3, 1),
+            set(1, 1),
+            SetRequest {
+                value: 7,
+                target: SetRequestTarget::Alias("x".to_string()),
+            },
+            set(3, 2),
+            set(2, 1),
+            set(1, 2),
+        ];
+
+        sort_and_dedup(&mut set_requests);
+
+        let (other, addresses) = set_requests.split_last().unwrap();
+        assert_eq!(values(addresses), vec![(1, 2), (2, 1), (3, 2)]);
+        assert!(matches!(&other.target, SetRequestTarget::Alias(alias) if alias == "x"));
+    }
+
+    #[test]
+    fn lifecycle_hooks_run_on_program_switches() {
+        let (_dir, mut runtime) = runtime_with_program(
+            r#"function setup()
+end
+
+level = LOW
+disabled = false
+
+function on_enable()
+    level = HIGH
+end
+
+function on_disable()
+    disabled = true
+end
+
+function tick(now)
+    set_alias("a", level)
+end
+"#,
+            test_util::options(),
+        );
+        // The first tick sees the value set by on_enable.
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, HIGH)]);
+
+        let fixture = runtime.get_fixture_mut("f").unwrap();
+        fixture.set_active_program("OFF").unwrap();
+        let globals = fixture.get_program("p").unwrap().debug_globals().unwrap();
+        assert_eq!(globals["disabled"], serde_json::json!(true));
+    }
+
+    #[test]
+    fn fade_in_ramps_outputs() {
+        let mut fade_in = FadeIn {
+            duration: Duration::from_millis(100),
+            start: None,
+            targets: BTreeMap::new(),
+        };
+        let start = Instant::now();
+
+        let mut set_requests = vec![set(1, 1000), set(2, 500)];
+        assert!(!fade_in.apply(start, &mut set_requests));
+        assert_eq!(values(&set_requests), vec![(1, 0), (2, 0)]);
+
+        // Targets are kept even if the program doesn't produce them again.
+        let mut set_requests = vec![set(2, 600)];
+        assert!(!fade_in.apply(start + Duration::from_millis(50), &mut set_requests));
+        assert_eq!(values(&set_requests), vec![(1, 500), (2, 300)]);
+
+        let mut set_requests = vec![];
+        assert!(fade_in.apply(start + Duration::from_millis(100), &mut set_requests));
+        assert_eq!(values(&set_requests), vec![(1, 1000), (2, 600)]);
+    }
+
+    #[test]
+    fn startup_fade_applies_to_loaded_fixtures() {
+        let (_dir, mut runtime) = runtime_with_program(
+            &constant_program("a", HIGH),
+            RuntimeOptions {
+                startup_fade: Duration::from_secs(3600),
+                ..test_util::options()
+            },
+        );
+
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, LOW)]);
+    }
+
+    #[test]
+    fn unchanged_fixture_outputs_are_suppressed() {
+        let (_dir, mut runtime) = runtime_with_program(
+            r#"function setup()
+end
+
+n = 0
+function tick(now)
+    n = math.min(n + 1, 2)
+    set_alias("a", n)
+end
+"#,
+            RuntimeOptions {
+                suppress_unchanged: true,
+                ..test_util::options()
+            },
+        );
+
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, 1)]);
+        assert_eq!(values(runtime.tick().unwrap()), vec![(1, 2)]);
+        assert!(runtime.tick().unwrap().is_empty());
+        assert!(runtime.tick().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn ticks_emit_spans() {
+        use std::sync::Mutex;
+        use tracing_subscriber::layer::{Context, SubscriberExt};
+        use tracing_subscriber::Layer;
+
+        /// Records the names of all spans created.
+        struct SpanNames(Arc<Mutex<Vec<String>>>);
+
+        impl<S: tracing::Subscriber> Layer<S> for SpanNames {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _id: &tracing::span::Id,
+                _ctx: Context<'_, S>,
+            ) {
+                let name = attrs.metadata().name().to_string();
+                self.0.lock().unwrap().push(name);
+            }
+        }
+
+        let (_dir, mut runtime) =
+            runtime_with_program(&constant_program("a", HIGH), test_util::options());
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry().with(SpanNames(names.clone()));
+
+        tracing::subscriber::with_default(subscriber, || runtime.tick().map(|_| ())).unwrap();
+
+        // One span for the runtime, and one for the fixture.
+        assert_eq!(*names.lock().unwrap(), vec!["tick", "tick"]);
+    }
 }
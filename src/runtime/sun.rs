@@ -0,0 +1,59 @@
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+
+/// Julian date of the Unix epoch.
+const JULIAN_DATE_UNIX_EPOCH: f64 = 2440587.5;
+/// Julian date of J2000.0, i.e., 2000-01-01 12:00 UTC.
+const JULIAN_DATE_J2000: f64 = 2451545.0;
+
+/// Local times of sunrise and sunset on a day, in seconds since local midnight.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SunTimes {
+    pub(crate) sunrise: i64,
+    pub(crate) sunset: i64,
+}
+
+/// Calculates the times of sunrise and sunset on the given local date at the given location,
+/// in degrees, with longitudes east of Greenwich being positive.
+/// This uses the sunrise equation as described by
+/// <https://en.wikipedia.org/wiki/Sunrise_equation>, which is accurate to about a minute.
+/// Returns `None` if the sun doesn't rise or set on that day, i.e., during polar day or night.
+pub(crate) fn sun_times(date: NaiveDate, latitude: f64, longitude: f64) -> Option<SunTimes> {
+    let unix_days = (date - NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as f64;
+    // Current Julian day, i.e., days since J2000.0.
+    let n = (unix_days + JULIAN_DATE_UNIX_EPOCH - JULIAN_DATE_J2000 + 0.0008).ceil();
+    // Mean solar time.
+    let j_star = n - longitude / 360.0;
+    // Solar mean anomaly.
+    let m = (357.5291 + 0.98560028 * j_star).rem_euclid(360.0);
+    let m_rad = m.to_radians();
+    // Equation of the center.
+    let c = 1.9148 * m_rad.sin() + 0.02 * (2.0 * m_rad).sin() + 0.0003 * (3.0 * m_rad).sin();
+    // Ecliptic longitude.
+    let lambda = (m + c + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+    let transit = JULIAN_DATE_J2000 + j_star + 0.0053 * m_rad.sin() - 0.0069 * (2.0 * lambda).sin();
+    // Declination of the sun.
+    let sin_declination = lambda.sin() * 23.4397_f64.to_radians().sin();
+    let cos_declination = sin_declination.asin().cos();
+    // Hour angle, accounting for refraction and the size of the solar disc.
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = ((-0.833_f64).to_radians().sin() - latitude.sin() * sin_declination)
+        / (latitude.cos() * cos_declination);
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+
+    let midnight = Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()?;
+    let seconds_since_midnight = |julian_date: f64| {
+        let unix_seconds = ((julian_date - JULIAN_DATE_UNIX_EPOCH) * 86400.0).round() as i64;
+        let time: DateTime<Local> = Utc.timestamp_opt(unix_seconds, 0).single()?.into();
+        Some((time - midnight).num_seconds())
+    };
+
+    Some(SunTimes {
+        sunrise: seconds_since_midnight(transit - hour_angle / 360.0)?,
+        sunset: seconds_since_midnight(transit + hour_angle / 360.0)?,
+    })
+}
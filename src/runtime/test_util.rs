@@ -0,0 +1,135 @@
+//! Helpers to set up fixtures, programs, and runtimes in tests.
+
+use crate::runtime::fixture::{Fixture, SelectedPrograms, VERSION};
+use crate::runtime::runtime::{Runtime, RuntimeOptions};
+use alloy::config::UniverseConfig;
+use alloy::OutputValue;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Builds a universe with one device and the given outputs, e.g.
+/// `json!([{"alias": "a", "address": 1}])`.
+pub(crate) fn universe(outputs: serde_json::Value) -> UniverseConfig {
+    serde_json::from_value(serde_json::json!({ "devices": [{ "outputs": outputs }] }))
+        .expect("invalid universe config")
+}
+
+/// Builds a universe with one output per alias, at addresses counting up from 1.
+pub(crate) fn universe_with_aliases(aliases: &[&str]) -> UniverseConfig {
+    universe(
+        aliases
+            .iter()
+            .enumerate()
+            .map(|(i, alias)| serde_json::json!({ "alias": alias, "address": i + 1 }))
+            .collect(),
+    )
+}
+
+/// Runtime options with all optional behavior disabled.
+pub(crate) fn options() -> RuntimeOptions {
+    RuntimeOptions {
+        warning_interval: Duration::from_secs(1),
+        startup_fade: Duration::ZERO,
+        program_fade: Duration::ZERO,
+        suppress_unchanged: false,
+        send_changed_only: false,
+        parallel_ticks: false,
+        state_file: None,
+        allow_output_overlap: false,
+        strict_outputs: false,
+        alias_overrides: HashMap::new(),
+        location: None,
+    }
+}
+
+/// A temporary fixtures root.
+pub(crate) struct FixturesDir {
+    dir: TempDir,
+}
+
+impl FixturesDir {
+    pub(crate) fn new() -> FixturesDir {
+        FixturesDir {
+            dir: tempfile::tempdir().expect("unable to create temporary directory"),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes a file relative to the root, creating directories as necessary.
+    pub(crate) fn write(&self, path: &str, contents: &str) -> PathBuf {
+        let path = self.dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).expect("unable to create directory");
+        fs::write(&path, contents).expect("unable to write file");
+        path
+    }
+
+    /// Writes the fixture `<name>.lua`, whose `setup()` sets its name and then runs the given
+    /// statements.
+    pub(crate) fn fixture(&self, name: &str, setup: &str) -> PathBuf {
+        self.write(
+            &format!("{}.lua", name),
+            &format!(
+                "SOURCE_VERSION={}\n\nfunction setup()\n    fixture_name(\"{}\")\n{}\nend\n",
+                VERSION, name, setup
+            ),
+        )
+    }
+
+    /// Writes a program to the given path, prefixed with the current `SOURCE_VERSION`.
+    pub(crate) fn program(&self, path: &str, source: &str) -> PathBuf {
+        self.write(path, &format!("SOURCE_VERSION={}\n\n{}", VERSION, source))
+    }
+
+    /// Loads the fixture `<name>.lua`.
+    pub(crate) fn load(&self, name: &str, universe_config: &UniverseConfig) -> Fixture {
+        Fixture::new(
+            self.dir.path().join(format!("{}.lua", name)),
+            universe_config,
+            &HashMap::new(),
+            &SelectedPrograms::default(),
+        )
+        .expect("unable to load fixture")
+    }
+
+    /// Sets up a runtime with all fixtures of this root.
+    pub(crate) fn runtime(
+        &self,
+        universe_config: &UniverseConfig,
+        options: RuntimeOptions,
+    ) -> Runtime {
+        Runtime::new(&[self.dir.path()], universe_config, options)
+            .expect("unable to set up runtime")
+    }
+}
+
+/// Source of a program which sets the given output to the given value on every tick.
+pub(crate) fn constant_program(alias: &str, value: OutputValue) -> String {
+    format!(
+        "function setup()\nend\n\nfunction tick(now)\n    set_alias(\"{}\", {})\nend\n",
+        alias, value
+    )
+}
+
+/// Sets up a runtime with the fixture `f`, which has the output `a` at address 1 and runs the
+/// program `p` with the given source.
+pub(crate) fn runtime_with_program(
+    source: &str,
+    options: RuntimeOptions,
+) -> (FixturesDir, Runtime) {
+    let dir = FixturesDir::new();
+    dir.fixture("f", r#"add_output_alias("a") add_program("p", "f/p.lua")"#);
+    dir.program("f/p.lua", source);
+    let mut runtime = dir.runtime(&universe_with_aliases(&["a"]), options);
+    runtime
+        .get_fixture_mut("f")
+        .unwrap()
+        .set_active_program("p")
+        .unwrap();
+    (dir, runtime)
+}
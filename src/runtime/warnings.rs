@@ -0,0 +1,106 @@
+use log::warn;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// The number of distinct warnings to track before forgetting about stale ones.
+const MAX_TRACKED_WARNINGS: usize = 1024;
+
+/// Rate-limits identical warnings, emitting each at most once per interval.
+/// Warnings suppressed in the meantime are summarized the next time the warning is emitted.
+pub(crate) struct WarningLimiter {
+    interval: Duration,
+    warnings: HashMap<u64, TrackedWarning>,
+}
+
+struct TrackedWarning {
+    last_emitted: Instant,
+    suppressed: u64,
+}
+
+impl WarningLimiter {
+    pub(crate) fn new(interval: Duration) -> WarningLimiter {
+        WarningLimiter {
+            interval,
+            warnings: HashMap::new(),
+        }
+    }
+
+    /// Logs a warning for the given source, unless the same warning was logged for the same
+    /// source within the interval.
+    pub(crate) fn warn(&mut self, source: &str, message: &str) {
+        match self.record(source, message, Instant::now()) {
+            Some(0) => warn!("{}: {}", source, message),
+            Some(suppressed) => warn!("{}: {} (repeated {} times)", source, message, suppressed),
+            None => {}
+        }
+    }
+
+    /// Records an occurrence of a warning at the given time.
+    /// Returns the number of occurrences suppressed since the warning was last emitted, if it
+    /// should be emitted now.
+    fn record(&mut self, source: &str, message: &str, now: Instant) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        message.hash(&mut hasher);
+        let key = hasher.finish();
+
+        if let Some(w) = self.warnings.get_mut(&key) {
+            if now.duration_since(w.last_emitted) < self.interval {
+                w.suppressed += 1;
+                return None;
+            }
+            let suppressed = w.suppressed;
+            w.last_emitted = now;
+            w.suppressed = 0;
+            return Some(suppressed);
+        }
+
+        if self.warnings.len() >= MAX_TRACKED_WARNINGS {
+            let interval = self.interval;
+            self.warnings
+                .retain(|_, w| now.duration_since(w.last_emitted) < interval);
+        }
+
+        self.warnings.insert(
+            key,
+            TrackedWarning {
+                last_emitted: now,
+                suppressed: 0,
+            },
+        );
+        Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_warnings_are_collapsed() {
+        let mut limiter = WarningLimiter::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(limiter.record("f", "boom", at(0)), Some(0));
+        assert_eq!(limiter.record("f", "boom", at(1)), None);
+        assert_eq!(limiter.record("f", "boom", at(2)), None);
+        // Other warnings are tracked separately.
+        assert_eq!(limiter.record("f", "bang", at(2)), Some(0));
+        assert_eq!(limiter.record("g", "boom", at(2)), Some(0));
+
+        assert_eq!(limiter.record("f", "boom", at(10)), Some(2));
+        assert_eq!(limiter.record("f", "boom", at(11)), None);
+    }
+
+    #[test]
+    fn zero_interval_emits_every_warning() {
+        let mut limiter = WarningLimiter::new(Duration::ZERO);
+        let now = Instant::now();
+
+        assert_eq!(limiter.record("f", "boom", now), Some(0));
+        assert_eq!(limiter.record("f", "boom", now), Some(0));
+    }
+}
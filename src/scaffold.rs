@@ -0,0 +1,83 @@
+use crate::runtime::fixture::{validate_fixture_name, VERSION};
+use anyhow::{ensure, Context, Result};
+use log::info;
+use std::fs;
+use std::path::Path;
+
+const FIXTURE_TEMPLATE_SOURCE: &str = include_str!("runtime/lua/fixture_template.lua");
+const PROGRAM_TEMPLATE_SOURCE: &str = include_str!("runtime/lua/program_template.lua");
+
+/// Writes a new fixture `<name>.lua` and a stub program `<name>/default.lua` to the fixtures
+/// directory.
+/// Existing files are never overwritten.
+pub(crate) fn new_fixture<P: AsRef<Path>>(fixtures_root: P, name: &str) -> Result<()> {
+    validate_fixture_name(name)?;
+
+    let fixture_path = fixtures_root.as_ref().join(format!("{}.lua", name));
+    let program_dir = fixtures_root.as_ref().join(name);
+    let program_path = program_dir.join("default.lua");
+    ensure!(
+        !fixture_path.exists(),
+        "fixture file {:?} already exists",
+        fixture_path
+    );
+    ensure!(
+        !program_path.exists(),
+        "program file {:?} already exists",
+        program_path
+    );
+
+    fs::create_dir_all(&program_dir).context("unable to create program directory")?;
+    fs::write(
+        &fixture_path,
+        render_template(FIXTURE_TEMPLATE_SOURCE, name),
+    )
+    .context("unable to write fixture file")?;
+    info!("wrote fixture to {:?}", fixture_path);
+    fs::write(
+        &program_path,
+        render_template(PROGRAM_TEMPLATE_SOURCE, name),
+    )
+    .context("unable to write program file")?;
+    info!("wrote program to {:?}", program_path);
+
+    Ok(())
+}
+
+fn render_template(template: &str, name: &str) -> String {
+    template
+        .replace("$VERSION", &VERSION.to_string())
+        .replace("$NAME", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::fixture::{Fixture, SelectedPrograms};
+    use crate::runtime::test_util;
+    use std::collections::HashMap;
+
+    #[test]
+    fn scaffolded_fixture_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        new_fixture(dir.path(), "lamp").unwrap();
+
+        let fixture = Fixture::new(
+            dir.path().join("lamp.lua"),
+            &test_util::universe_with_aliases(&[]),
+            &HashMap::new(),
+            &SelectedPrograms::default(),
+        )
+        .unwrap();
+        assert_eq!(fixture.name, "lamp");
+        assert!(fixture.get_program("default").is_some());
+    }
+
+    #[test]
+    fn scaffolding_does_not_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        new_fixture(dir.path(), "lamp").unwrap();
+
+        assert!(new_fixture(dir.path(), "lamp").is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// Statistics about the tick loop, shared with the HTTP API.
+/// Averages are over the last completed statistics interval.
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct TickStats {
+    /// Average time to tick all fixtures, in microseconds.
+    pub(crate) tick_duration_avg_micros: f64,
+    /// Average time to send set requests to Submarine, in microseconds.
+    pub(crate) send_duration_avg_micros: f64,
+    pub(crate) ticks_per_second: f64,
+    /// Number of ticks which took longer than the tick interval.
+    pub(crate) overruns: u64,
+    /// When the statistics interval ended, if one did yet.
+    pub(crate) timestamp: Option<DateTime<Local>>,
+    /// The most recent failure of the tick loop, i.e., a failed tick or send.
+    pub(crate) last_error: Option<TickLoopError>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TickLoopError {
+    pub(crate) message: String,
+    pub(crate) timestamp: DateTime<Local>,
+}
+
+impl TickStats {
+    pub(crate) fn record_error(&mut self, err: &anyhow::Error) {
+        self.last_error = Some(TickLoopError {
+            message: format!("{:#}", err),
+            timestamp: Local::now(),
+        });
+    }
+}
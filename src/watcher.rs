@@ -0,0 +1,68 @@
+use crate::runtime::runtime::Runtime;
+use crate::Result;
+use alloy::config::UniverseConfig;
+use anyhow::{bail, Context};
+use arc_swap::ArcSwap;
+use log::{debug, info, warn};
+use notify::event::EventKind;
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+
+/// Time without further changes after which changed fixtures are reloaded.
+/// Editors often write files in multiple steps, and rapid successive saves should result in
+/// only one reload.
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches the fixtures directories and reloads fixtures whose sources, including the sources of
+/// their programs, change.
+/// Fixtures which fail to reload keep running in their previous version.
+pub(crate) async fn watch_fixtures(
+    fixtures_paths: Vec<PathBuf>,
+    runtime: Arc<Mutex<Runtime>>,
+    universe: Arc<ArcSwap<UniverseConfig>>,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        match res {
+            Ok(event) => {
+                if let EventKind::Create(_) | EventKind::Modify(_) = event.kind {
+                    for path in event.paths {
+                        // This only fails if the receiver is gone, in which case we're shutting down.
+                        let _ = tx.send(path);
+                    }
+                }
+            }
+            Err(err) => warn!("fixture watcher failed: {:?}", err),
+        }
+    })
+    .context("unable to create file watcher")?;
+    for fixtures_path in fixtures_paths.iter() {
+        watcher
+            .watch(fixtures_path, RecursiveMode::Recursive)
+            .context(format!("unable to watch {:?}", fixtures_path))?;
+        info!("watching {:?} for changes", fixtures_path);
+    }
+
+    while let Some(path) = rx.recv().await {
+        let mut changed = HashSet::from([path]);
+        while let Ok(Some(path)) = tokio::time::timeout(DEBOUNCE_INTERVAL, rx.recv()).await {
+            changed.insert(path);
+        }
+        debug!("fixture sources changed: {:?}", changed);
+
+        let affected = runtime.lock().await.fixtures_affected_by(&changed);
+        for name in affected {
+            info!("sources of fixture {} changed, reloading", name);
+            // This only locks the runtime briefly.
+            if let Err(err) = Runtime::reload_fixture(&runtime, &name, universe.load_full()).await {
+                warn!("unable to reload fixture {}: {:?}", name, err)
+            }
+        }
+    }
+
+    bail!("file watcher stopped")
+}